@@ -1,6 +1,7 @@
 use anyhow::{Result, anyhow};
 use regex::Regex;
 use std::cmp::PartialEq;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{self, Read};
 
@@ -13,20 +14,20 @@ fn read_instructions(file: File) -> Result<String> {
     Ok(corrupted_instructions)
 }
 
-fn part_1(corrupted_instructions: &str) -> Result<i32> {
+fn part_1(corrupted_instructions: &str) -> Result<u64> {
     let regex = Regex::new(r"mul\((?P<num1>\d{1,3}),(?P<num2>\d{1,3})\)")?;
 
     let sum = regex
         .captures_iter(corrupted_instructions)
         .map(|caps| {
-            let num1: i32 = caps["num1"].parse()?;
-            let num2: i32 = caps["num2"].parse()?;
+            let num1: u64 = caps["num1"].parse()?;
+            let num2: u64 = caps["num2"].parse()?;
 
             Ok(num1 * num2)
         })
-        .collect::<Result<Vec<i32>>>()?
+        .collect::<Result<Vec<u64>>>()?
         .into_iter()
-        .fold(0, |acc, x| acc + x);
+        .sum();
 
     Ok(sum)
 }
@@ -39,7 +40,7 @@ fn check_part_1() {
     assert_eq!(part_1(corrupted_instructions).unwrap(), 161);
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum Command {
     Do,
     Dont,
@@ -57,26 +58,172 @@ impl std::str::FromStr for Command {
     }
 }
 
-fn part_2(corrputed_instructions: &str) -> Result<i32> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InstructionKind {
+    Mul(u64, u64),
+    Toggle(Command),
+}
+
+///A recognised instruction together with where it was found in the corrupted memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Annotation {
+    offset: usize,
+    length: usize,
+    line: usize,
+    column: usize,
+    kind: InstructionKind,
+    enabled: bool,
+}
+
+impl Annotation {
+    fn product(&self) -> Option<u64> {
+        match self.kind {
+            InstructionKind::Mul(num1, num2) => Some(num1 * num2),
+            InstructionKind::Toggle(_) => None,
+        }
+    }
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{} (byte {}) ", self.line, self.column, self.offset)?;
+        let state = if self.enabled { "enabled" } else { "disabled" };
+        match self.kind {
+            InstructionKind::Mul(num1, num2) => {
+                write!(f, "mul({},{}) = {} [{}]", num1, num2, num1 * num2, state)
+            }
+            InstructionKind::Toggle(Command::Do) => write!(f, "do() [{}]", state),
+            InstructionKind::Toggle(Command::Dont) => write!(f, "don't() [{}]", state),
+        }
+    }
+}
+
+///Every `mul`, `do()` and `don't()` in order, with the enabled state in effect after it
+fn annotate(corrupted_instructions: &str) -> Result<Vec<Annotation>> {
     let regex =
         Regex::new(r"mul\((?P<num1>\d{1,3}),(?P<num2>\d{1,3})\)|(?P<cmd>do\(\)|don't\(\))")?;
     let mut last_command = Command::Do;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut scanned = 0;
 
-    let sum = regex
-        .captures_iter(corrputed_instructions)
+    regex
+        .captures_iter(corrupted_instructions)
         .map(|caps| {
-            if let Some(cmd) = caps.name("cmd") {
-                last_command = cmd.as_str().parse()?;
-            } else if last_command == Command::Do {
-                let num1: i32 = caps["num1"].parse()?;
-                let num2: i32 = caps["num2"].parse()?;
-                return Ok(num1 * num2);
+            let whole = caps.get(0).ok_or_else(|| anyhow!("Empty match"))?;
+            let offset = whole.start();
+
+            for (i, byte) in corrupted_instructions.as_bytes()[scanned..offset]
+                .iter()
+                .enumerate()
+            {
+                if *byte == b'\n' {
+                    line += 1;
+                    line_start = scanned + i + 1;
+                }
             }
-            Ok(0)
+            scanned = offset;
+            let column = corrupted_instructions[line_start..offset].chars().count() + 1;
+
+            let kind = if let Some(cmd) = caps.name("cmd") {
+                last_command = cmd.as_str().parse()?;
+                InstructionKind::Toggle(last_command)
+            } else {
+                InstructionKind::Mul(caps["num1"].parse()?, caps["num2"].parse()?)
+            };
+
+            Ok(Annotation {
+                offset,
+                length: whole.len(),
+                line,
+                column,
+                kind,
+                enabled: last_command == Command::Do,
+            })
         })
-        .collect::<Result<Vec<i32>>>()?
-        .into_iter()
-        .fold(0, |acc, x| acc + x);
+        .collect()
+}
+
+#[test]
+fn check_annotate() {
+    let corrupted_instructions =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](\nmul(11,8)undo()?mul(8,5))";
+    let annotations = annotate(corrupted_instructions).unwrap();
+
+    assert_eq!(annotations.len(), 6);
+    assert_eq!(
+        annotations[0].to_string(),
+        "1:2 (byte 1) mul(2,4) = 8 [enabled]"
+    );
+    assert_eq!(
+        annotations[2].to_string(),
+        "1:29 (byte 28) mul(5,5) = 25 [disabled]"
+    );
+    assert_eq!(
+        annotations[3].to_string(),
+        "2:1 (byte 49) mul(11,8) = 88 [disabled]"
+    );
+    assert_eq!(annotations[4].kind, InstructionKind::Toggle(Command::Do));
+    assert_eq!(annotations[5].product(), Some(40));
+    assert!(annotations[5].enabled);
+}
+
+///Re-prints the memory with enabled calls in green, disabled calls in red, `do()`/`don't()` in yellow and noise dimmed
+fn highlight(corrupted_instructions: &str, annotations: &[Annotation]) -> String {
+    const GREEN: &str = "\x1b[32m";
+    const RED: &str = "\x1b[31m";
+    const YELLOW: &str = "\x1b[33m";
+    const DIM: &str = "\x1b[2m";
+    const RESET: &str = "\x1b[0m";
+
+    let mut highlighted = String::with_capacity(corrupted_instructions.len() * 2);
+    let mut position = 0;
+
+    for annotation in annotations {
+        let end = annotation.offset + annotation.length;
+        if annotation.offset > position {
+            highlighted.push_str(DIM);
+            highlighted.push_str(&corrupted_instructions[position..annotation.offset]);
+            highlighted.push_str(RESET);
+        }
+
+        let color = match (annotation.kind, annotation.enabled) {
+            (InstructionKind::Toggle(_), _) => YELLOW,
+            (InstructionKind::Mul(..), true) => GREEN,
+            (InstructionKind::Mul(..), false) => RED,
+        };
+        highlighted.push_str(color);
+        highlighted.push_str(&corrupted_instructions[annotation.offset..end]);
+        highlighted.push_str(RESET);
+        position = end;
+    }
+
+    if position < corrupted_instructions.len() {
+        highlighted.push_str(DIM);
+        highlighted.push_str(&corrupted_instructions[position..]);
+        highlighted.push_str(RESET);
+    }
+
+    highlighted
+}
+
+#[test]
+fn check_highlight() {
+    let corrupted_instructions = "xmul(2,4)don't()mul(1,1)";
+    let annotations = annotate(corrupted_instructions).unwrap();
+
+    assert_eq!(
+        highlight(corrupted_instructions, &annotations),
+        "\x1b[2mx\x1b[0m\x1b[32mmul(2,4)\x1b[0m\x1b[33mdon't()\x1b[0m\x1b[31mmul(1,1)\x1b[0m"
+    );
+}
+
+fn part_2(corrupted_instructions: &str) -> Result<u64> {
+    let sum = annotate(corrupted_instructions)?
+        .iter()
+        .filter(|annotation| annotation.enabled)
+        .filter_map(Annotation::product)
+        .sum();
 
     Ok(sum)
 }
@@ -88,16 +235,189 @@ fn check_part_2() {
     assert_eq!(part_2(corrupted_instructions).unwrap(), 48);
 }
 
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    Idle,
+    M,
+    Mu,
+    Mul,
+    Num1,
+    Num2,
+    D,
+    Do,
+    DoOpen,
+    Don,
+    DonApostrophe,
+    DonT,
+    DontOpen,
+}
+
+///Byte-level state machine equivalent to the part-2 regex. All state lives in the struct, so an
+///instruction split across two chunks is picked up where the previous chunk left off.
+#[derive(Debug)]
+struct Scanner {
+    state: ScanState,
+    num1: u64,
+    num2: u64,
+    digits: u8,
+    enabled: bool,
+    part_1: u64,
+    part_2: u64,
+}
+
+impl Scanner {
+    fn new() -> Self {
+        Scanner {
+            state: ScanState::Idle,
+            num1: 0,
+            num2: 0,
+            digits: 0,
+            enabled: true,
+            part_1: 0,
+            part_2: 0,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            if !self.step(byte) {
+                //None of the instructions contain an 'm' or a 'd' after their first byte, so a
+                //failed candidate never hides the start of another one except at this byte
+                self.state = ScanState::Idle;
+                self.step(byte);
+            }
+        }
+    }
+
+    ///Returns false if `byte` cannot continue the current candidate
+    fn step(&mut self, byte: u8) -> bool {
+        self.state = match (self.state, byte) {
+            (ScanState::Idle, b'm') => ScanState::M,
+            (ScanState::Idle, b'd') => ScanState::D,
+            (ScanState::Idle, _) => ScanState::Idle,
+            (ScanState::M, b'u') => ScanState::Mu,
+            (ScanState::Mu, b'l') => ScanState::Mul,
+            (ScanState::Mul, b'(') => {
+                self.num1 = 0;
+                self.num2 = 0;
+                self.digits = 0;
+                ScanState::Num1
+            }
+            (ScanState::Num1 | ScanState::Num2, b'0'..=b'9') if self.digits < 3 => {
+                let num = if self.state == ScanState::Num1 {
+                    &mut self.num1
+                } else {
+                    &mut self.num2
+                };
+                *num = *num * 10 + (byte - b'0') as u64;
+                self.digits += 1;
+                self.state
+            }
+            (ScanState::Num1, b',') if self.digits > 0 => {
+                self.digits = 0;
+                ScanState::Num2
+            }
+            (ScanState::Num2, b')') if self.digits > 0 => {
+                let product = self.num1 * self.num2;
+                self.part_1 += product;
+                if self.enabled {
+                    self.part_2 += product;
+                }
+                ScanState::Idle
+            }
+            (ScanState::D, b'o') => ScanState::Do,
+            (ScanState::Do, b'(') => ScanState::DoOpen,
+            (ScanState::Do, b'n') => ScanState::Don,
+            (ScanState::DoOpen, b')') => {
+                self.enabled = true;
+                ScanState::Idle
+            }
+            (ScanState::Don, b'\'') => ScanState::DonApostrophe,
+            (ScanState::DonApostrophe, b't') => ScanState::DonT,
+            (ScanState::DonT, b'(') => ScanState::DontOpen,
+            (ScanState::DontOpen, b')') => {
+                self.enabled = false;
+                ScanState::Idle
+            }
+            _ => return false,
+        };
+
+        true
+    }
+}
+
+///Runs both parts over `reader` in `chunk_size` pieces, so memory use does not grow with the input
+fn scan<R: Read>(mut reader: R, chunk_size: usize) -> Result<(u64, u64)> {
+    let mut scanner = Scanner::new();
+    let mut buffer = vec![0; chunk_size];
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        scanner.feed(&buffer[..read]);
+    }
+
+    Ok((scanner.part_1, scanner.part_2))
+}
+
+#[test]
+fn check_scan() {
+    let corrupted_instructions = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))mmul(1,1)mul(1234,1)";
+
+    //Every chunk size splits some instruction across a boundary
+    for chunk_size in 1..=corrupted_instructions.len() {
+        assert_eq!(
+            scan(corrupted_instructions.as_bytes(), chunk_size).unwrap(),
+            (
+                part_1(corrupted_instructions).unwrap(),
+                part_2(corrupted_instructions).unwrap()
+            )
+        );
+    }
+    assert_eq!(
+        scan(corrupted_instructions.as_bytes(), CHUNK_SIZE).unwrap(),
+        (162, 49)
+    );
+}
+
 fn main() -> Result<()> {
     let file = File::open("inputs/input03.txt")?;
-    let corrupted_instructions = read_instructions(file)?;
+
+    //`--annotate` needs the whole memory in one buffer, so it reads it in one go and takes the
+    //answers from that buffer; otherwise the memory is streamed
+    let annotated = std::env::args().any(|arg| arg == "--annotate");
+    let (part_1_sum, part_2_sum, corrupted_instructions) = if annotated {
+        let corrupted_instructions = read_instructions(file)?;
+        (
+            part_1(&corrupted_instructions)?,
+            part_2(&corrupted_instructions)?,
+            Some(corrupted_instructions),
+        )
+    } else {
+        let (part_1_sum, part_2_sum) = scan(file, CHUNK_SIZE)?;
+        (part_1_sum, part_2_sum, None)
+    };
 
     //Part-1
-    println!("{}", part_1(&corrupted_instructions)?);
+    println!("{}", part_1_sum);
     //192767529
 
     //Part-2
-    println!("{}", part_2(&corrupted_instructions)?);
+    println!("{}", part_2_sum);
+
+    if let Some(corrupted_instructions) = corrupted_instructions {
+        let annotations = annotate(&corrupted_instructions)?;
+        for annotation in &annotations {
+            println!("{}", annotation);
+        }
+        println!("{}", highlight(&corrupted_instructions, &annotations));
+    }
 
     Ok(())
 }
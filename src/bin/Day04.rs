@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::{self, BufRead};
 
@@ -16,52 +16,308 @@ fn get_puzzle(file: File) -> Result<Vec<Vec<char>>> {
     Ok(matrix)
 }
 
-fn find_occurrences(puzzle: &Vec<Vec<char>>, row: usize, col: usize) -> usize {
-    let rows = puzzle.len() as isize;
-    let cols = puzzle[0].len() as isize;
-
-    const TARGET: [char; 4] = ['X', 'M', 'A', 'S'];
-    const DIRECTIONS: [(isize, isize); 8] = [
-        (0, 1),
-        (0, -1),
-        (1, 0),
-        (-1, 0),
-        (1, 1),
-        (1, -1),
-        (-1, 1),
-        (-1, -1),
-    ];
+const DIRECTIONS: [(isize, isize); 8] = [
+    (0, 1),
+    (0, -1),
+    (1, 0),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
 
-    let is_valid = |row: isize, col: isize, dr: isize, dc: isize| {
-        (0..TARGET.len()).all(|i| {
-            let new_row = row + i as isize * dr;
-            let new_col = col + i as isize * dc;
-            new_row >= 0
-                && new_col >= 0
-                && new_row < rows
-                && new_col < cols
-                && puzzle[new_row as usize][new_col as usize] == TARGET[i]
-        })
-    };
+///Aho-Corasick automaton over the letters of all the searched words
+struct Automaton {
+    goto: Vec<HashMap<char, usize>>,
+    fail: Vec<usize>,
+    //Indices of the words that end in each state
+    outputs: Vec<Vec<usize>>,
+}
 
-    DIRECTIONS
-        .iter()
-        .filter(|&&(dr, dc)| is_valid(row as isize, col as isize, dr, dc))
-        .count()
+impl Automaton {
+    fn new(words: &[&str]) -> Self {
+        let mut goto = vec![HashMap::new()];
+        let mut outputs = vec![Vec::new()];
+
+        for (index, word) in words.iter().enumerate() {
+            let mut state = 0;
+            for c in word.chars() {
+                state = match goto[state].get(&c) {
+                    Some(&next) => next,
+                    None => {
+                        goto.push(HashMap::new());
+                        outputs.push(Vec::new());
+                        let next = goto.len() - 1;
+                        goto[state].insert(c, next);
+                        next
+                    }
+                };
+            }
+            outputs[state].push(index);
+        }
+
+        //BFS so that the failure link of a state is known before its children are processed
+        let mut fail = vec![0; goto.len()];
+        let mut queue: VecDeque<usize> = goto[0].values().copied().collect();
+        while let Some(state) = queue.pop_front() {
+            let children: Vec<(char, usize)> = goto[state].iter().map(|(&c, &s)| (c, s)).collect();
+            for (c, child) in children {
+                let mut fallback = fail[state];
+                while fallback != 0 && !goto[fallback].contains_key(&c) {
+                    fallback = fail[fallback];
+                }
+                fail[child] = match goto[fallback].get(&c) {
+                    Some(&next) if next != child => next,
+                    _ => 0,
+                };
+                let inherited = outputs[fail[child]].clone();
+                outputs[child].extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        Automaton {
+            goto,
+            fail,
+            outputs,
+        }
+    }
+
+    fn next(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(&next) = self.goto[state].get(&c) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+}
+
+///How the edges of the grid behave when a word or template runs over them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Topology {
+    Bounded,
+    //Both the rows and the columns wrap around
+    Torus,
+    //Only the columns wrap around, the top and bottom edges are still bounded
+    Cylinder,
+}
+
+impl std::str::FromStr for Topology {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "bounded" => Ok(Topology::Bounded),
+            "torus" => Ok(Topology::Torus),
+            "cylinder" => Ok(Topology::Cylinder),
+            _ => Err(anyhow::anyhow!("Invalid Topology: {}", s)),
+        }
+    }
+}
+
+impl Topology {
+    ///The in-grid position `(row, col)` refers to, if any
+    fn wrap(self, row: isize, col: isize, rows: usize, cols: usize) -> Option<(usize, usize)> {
+        let (rows, cols) = (rows as isize, cols as isize);
+        if rows == 0 || cols == 0 {
+            return None;
+        }
+
+        let (row, col) = match self {
+            Topology::Bounded => (row, col),
+            Topology::Torus => (row.rem_euclid(rows), col.rem_euclid(cols)),
+            Topology::Cylinder => (row, col.rem_euclid(cols)),
+        };
+
+        ((0..rows).contains(&row) && (0..cols).contains(&col))
+            .then_some((row as usize, col as usize))
+    }
+
+    fn step(
+        self,
+        (row, col): (usize, usize),
+        (dr, dc): (isize, isize),
+        rows: usize,
+        cols: usize,
+    ) -> Option<(usize, usize)> {
+        self.wrap(row as isize + dr, col as isize + dc, rows, cols)
+    }
+}
+
+///Width of the widest row, shorter rows are treated as having holes at the end
+fn columns(puzzle: &[Vec<char>]) -> usize {
+    puzzle.iter().map(Vec::len).max().unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct WordMatch<'a> {
+    row: usize,
+    col: usize,
+    direction: (isize, isize),
+    word: &'a str,
+}
+
+impl WordMatch<'_> {
+    fn cells(&self, topology: Topology, rows: usize, cols: usize) -> Vec<(usize, usize)> {
+        let mut cells = vec![(self.row, self.col)];
+        for _ in 1..self.word.chars().count() {
+            match topology.step(cells[cells.len() - 1], self.direction, rows, cols) {
+                Some(next) => cells.push(next),
+                None => break,
+            }
+        }
+
+        cells
+    }
 }
 
-fn part_1(puzzle: &Vec<Vec<char>>) -> usize {
-    let mut count = 0;
+///Every occurrence of every word in any of the eight directions, found by running the automaton
+///along each straight line of the grid once per direction. On a wrapped topology a line can be a
+///cycle, in which case it is walked one extra word length so that matches crossing the seam are
+///found, and a word never reuses a cell of the cycle.
+fn find_words<'a>(
+    puzzle: &[Vec<char>],
+    words: &[&'a str],
+    topology: Topology,
+) -> Vec<WordMatch<'a>> {
+    let automaton = Automaton::new(words);
+    let lengths: Vec<usize> = words.iter().map(|word| word.chars().count()).collect();
+    let longest = lengths.iter().copied().max().unwrap_or(0);
+    let rows = puzzle.len();
+    let cols = columns(puzzle);
+
+    let mut matches = Vec::new();
+    for &(dr, dc) in &DIRECTIONS {
+        let mut visited = HashSet::new();
+        for row in 0..rows {
+            for col in 0..cols {
+                if visited.contains(&(row, col)) {
+                    continue;
+                }
+
+                //Walk backwards to the start of the line, or all the way round a cycle
+                let mut start = (row, col);
+                let mut cycle = None;
+                let mut length = 1;
+                while let Some(previous) = topology.step(start, (-dr, -dc), rows, cols) {
+                    if previous == (row, col) {
+                        cycle = Some(length);
+                        break;
+                    }
+                    start = previous;
+                    length += 1;
+                }
+
+                let mut line = vec![start];
+                loop {
+                    let last = line[line.len() - 1];
+                    match (cycle, topology.step(last, (dr, dc), rows, cols)) {
+                        (Some(n), Some(next)) if line.len() < n + longest - 1 => line.push(next),
+                        (None, Some(next)) => line.push(next),
+                        _ => break,
+                    }
+                }
+                let distinct = cycle.unwrap_or(line.len());
+                visited.extend(line.iter().take(distinct).copied());
 
-    for row in 0..puzzle.len() {
-        for col in 0..puzzle[0].len() {
-            if puzzle[row][col] == 'X' {
-                count += find_occurrences(puzzle, row, col);
+                let mut state = 0;
+                for (step, &(r, c)) in line.iter().enumerate() {
+                    state = match puzzle[r].get(c) {
+                        Some(&letter) => automaton.next(state, letter),
+                        None => 0,
+                    };
+                    for &index in &automaton.outputs[state] {
+                        let back = step + 1 - lengths[index];
+                        //A match starting past the first lap of a cycle was already reported
+                        if back >= distinct || lengths[index] > distinct {
+                            continue;
+                        }
+                        matches.push(WordMatch {
+                            row: line[back].0,
+                            col: line[back].1,
+                            direction: (dr, dc),
+                            word: words[index],
+                        });
+                    }
+                }
             }
         }
     }
 
-    count
+    matches
+}
+
+#[test]
+fn check_find_words() {
+    let puzzle: Vec<Vec<char>> = ["CATS", "AXTO", "TOAC", "SEAT"]
+        .iter()
+        .map(|line| line.chars().collect())
+        .collect();
+    let matches = find_words(&puzzle, &["CAT", "AT", "SEAT"], Topology::Bounded);
+
+    assert!(matches.contains(&WordMatch {
+        row: 0,
+        col: 0,
+        direction: (0, 1),
+        word: "CAT"
+    }));
+    assert!(matches.contains(&WordMatch {
+        row: 0,
+        col: 0,
+        direction: (1, 0),
+        word: "CAT"
+    }));
+    assert!(matches.contains(&WordMatch {
+        row: 3,
+        col: 0,
+        direction: (0, 1),
+        word: "SEAT"
+    }));
+    //"AT" is also reported inside "SEAT"
+    assert!(matches.contains(&WordMatch {
+        row: 3,
+        col: 2,
+        direction: (0, 1),
+        word: "AT"
+    }));
+    assert_eq!(matches.iter().filter(|m| m.word == "AT").count(), 6);
+    assert_eq!(matches.len(), 9);
+}
+
+///The grid with every letter that is not part of a match replaced by `.`
+fn render_matches(puzzle: &[Vec<char>], matches: &[WordMatch], topology: Topology) -> String {
+    let (rows, cols) = (puzzle.len(), columns(puzzle));
+    let matched: HashSet<(usize, usize)> = matches
+        .iter()
+        .flat_map(|word_match| word_match.cells(topology, rows, cols))
+        .collect();
+
+    puzzle
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            line.iter()
+                .enumerate()
+                .map(|(col, &c)| {
+                    if matched.contains(&(row, col)) {
+                        c
+                    } else {
+                        '.'
+                    }
+                })
+                .chain(std::iter::once('\n'))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+fn part_1(puzzle: &[Vec<char>], topology: Topology) -> usize {
+    find_words(puzzle, &["XMAS"], topology).len()
 }
 
 #[test]
@@ -78,39 +334,228 @@ fn check_part_1() {
         vec!['M', 'A', 'M', 'M', 'M', 'X', 'M', 'M', 'M', 'M'],
         vec!['M', 'X', 'M', 'X', 'A', 'X', 'M', 'A', 'S', 'X'],
     ];
-    assert_eq!(part_1(&puzzle), 18);
+    assert_eq!(part_1(&puzzle, Topology::Bounded), 18);
+    assert_eq!(
+        render_matches(
+            &puzzle,
+            &find_words(&puzzle, &["XMAS"], Topology::Bounded),
+            Topology::Bounded
+        ),
+        "....XXMAS.\n\
+         .SAMXMS...\n\
+         ...S..A...\n\
+         ..A.A.MS.X\n\
+         XMASAMX.MM\n\
+         X.....XA.A\n\
+         S.S.S.S.SS\n\
+         .A.A.A.A.A\n\
+         ..M.M.M.MM\n\
+         .X.X.XMASX\n"
+    );
+}
+
+///A small grid of letters where `None` cells match anything
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Template {
+    cells: Vec<Vec<Option<char>>>,
 }
 
-fn is_x_mas(puzzle: &Vec<Vec<char>>, row: usize, col: usize) -> bool {
-    const DIRECTIONS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, 1), (1, -1)];
-    let patterns: HashSet<&str> = ["MMSS", "MSSM", "SSMM", "SMMS"].iter().cloned().collect();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symmetry {
+    Exact,
+    Rotations,
+    RotationsAndReflections,
+}
 
-    patterns.contains(
-        DIRECTIONS
+impl Template {
+    fn parse(rows: &[&str], wildcard: char) -> Self {
+        let cells = rows
             .iter()
-            .map(|&(dr, dc)| puzzle[(row as isize + dr) as usize][(col as isize + dc) as usize])
-            .collect::<String>()
-            .as_str(),
-    )
+            .map(|row| row.chars().map(|c| (c != wildcard).then_some(c)).collect())
+            .collect();
+
+        Template { cells }
+    }
+
+    fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn width(&self) -> usize {
+        self.cells.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    fn cell(&self, row: usize, col: usize) -> Option<char> {
+        self.cells[row].get(col).copied().flatten()
+    }
+
+    ///Quarter turn clockwise
+    fn rotate(&self) -> Self {
+        let (height, width) = (self.height(), self.width());
+        let cells = (0..width)
+            .map(|row| {
+                (0..height)
+                    .map(|col| self.cell(height - 1 - col, row))
+                    .collect()
+            })
+            .collect();
+
+        Template { cells }
+    }
+
+    ///Mirror image along the vertical axis
+    fn reflect(&self) -> Self {
+        let width = self.width();
+        let cells = (0..self.height())
+            .map(|row| {
+                (0..width)
+                    .map(|col| self.cell(row, width - 1 - col))
+                    .collect()
+            })
+            .collect();
+
+        Template { cells }
+    }
+
+    fn matches_at(&self, puzzle: &[Vec<char>], row: usize, col: usize, topology: Topology) -> bool {
+        let (rows, cols) = (puzzle.len(), columns(puzzle));
+
+        self.cells.iter().enumerate().all(|(i, line)| {
+            line.iter()
+                .enumerate()
+                .all(|(j, &expected)| match expected {
+                    None => true,
+                    Some(letter) => {
+                        topology
+                            .wrap((row + i) as isize, (col + j) as isize, rows, cols)
+                            .and_then(|(r, c)| puzzle[r].get(c))
+                            == Some(&letter)
+                    }
+                })
+        })
+    }
 }
 
-fn part_2(puzzle: &Vec<Vec<char>>) -> usize {
-    let rows = puzzle.len();
-    let cols = puzzle[0].len();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Placement {
+    row: usize,
+    col: usize,
+    //Index into `TemplateQuery::orientations`
+    orientation: usize,
+}
+
+struct TemplateQuery {
+    orientations: Vec<Template>,
+}
+
+impl TemplateQuery {
+    fn new(template: Template, symmetry: Symmetry) -> Self {
+        let mut candidates = vec![template.clone()];
+        if symmetry != Symmetry::Exact {
+            for _ in 0..3 {
+                let next = candidates[candidates.len() - 1].rotate();
+                candidates.push(next);
+            }
+        }
+        if symmetry == Symmetry::RotationsAndReflections {
+            let reflected: Vec<Template> = candidates.iter().map(Template::reflect).collect();
+            candidates.extend(reflected);
+        }
 
-    let mut count = 0;
+        //Symmetric templates would otherwise report the same placement more than once
+        let mut seen = HashSet::new();
+        let orientations = candidates
+            .into_iter()
+            .filter(|candidate| seen.insert(candidate.clone()))
+            .collect();
 
-    for row in 1..(rows - 1) {
-        for col in 1..(cols - 1) {
-            if puzzle[row][col] == 'A' {
-                if is_x_mas(&puzzle, row, col) {
-                    count += 1;
+        TemplateQuery { orientations }
+    }
+
+    ///Every top-left position where some orientation of the template fits in the puzzle. A
+    ///template never overlaps itself, even on a wrapped grid smaller than it.
+    fn find(&self, puzzle: &[Vec<char>], topology: Topology) -> Vec<Placement> {
+        let rows = puzzle.len();
+        let cols = columns(puzzle);
+        let mut placements = Vec::new();
+
+        for (orientation, template) in self.orientations.iter().enumerate() {
+            if template.height() > rows || template.width() > cols {
+                continue;
+            }
+            let last_row = match topology {
+                Topology::Torus => rows - 1,
+                Topology::Bounded | Topology::Cylinder => rows - template.height(),
+            };
+            let last_col = match topology {
+                Topology::Torus | Topology::Cylinder => cols - 1,
+                Topology::Bounded => cols - template.width(),
+            };
+            for row in 0..=last_row {
+                for col in 0..=last_col {
+                    if template.matches_at(puzzle, row, col, topology) {
+                        placements.push(Placement {
+                            row,
+                            col,
+                            orientation,
+                        });
+                    }
                 }
             }
         }
+
+        placements
     }
+}
+
+#[test]
+fn check_template_query() {
+    let x_mas = TemplateQuery::new(
+        Template::parse(&["M.S", ".A.", "M.S"], '.'),
+        Symmetry::RotationsAndReflections,
+    );
+    assert_eq!(x_mas.orientations.len(), 4);
+
+    let corner = Template::parse(&["AB", "C."], '.');
+    assert_eq!(
+        TemplateQuery::new(corner.clone(), Symmetry::RotationsAndReflections)
+            .orientations
+            .len(),
+        8
+    );
 
-    count
+    let puzzle: Vec<Vec<char>> = ["ABX", "CAC", "XBA"]
+        .iter()
+        .map(|line| line.chars().collect())
+        .collect();
+    assert_eq!(
+        TemplateQuery::new(corner.clone(), Symmetry::Exact).find(&puzzle, Topology::Bounded),
+        vec![Placement {
+            row: 0,
+            col: 0,
+            orientation: 0
+        }]
+    );
+    //Every 2x2 window holds the corner in one of its four rotations
+    let rotated = TemplateQuery::new(corner, Symmetry::Rotations);
+    let placements = rotated.find(&puzzle, Topology::Bounded);
+    assert_eq!(placements.len(), 4);
+    assert!(
+        placements
+            .iter()
+            .any(|placement| (placement.row, placement.col) == (1, 1)
+                && rotated.orientations[placement.orientation]
+                    == Template::parse(&[".C", "BA"], '.'))
+    );
+}
+
+fn part_2(puzzle: &[Vec<char>], topology: Topology) -> usize {
+    TemplateQuery::new(
+        Template::parse(&["M.S", ".A.", "M.S"], '.'),
+        Symmetry::Rotations,
+    )
+    .find(puzzle, topology)
+    .len()
 }
 
 #[test]
@@ -127,19 +572,86 @@ fn check_part_2() {
         vec!['M', '.', 'M', '.', 'M', '.', 'M', '.', 'M', '.'],
         vec!['.', '.', '.', '.', '.', '.', '.', '.', '.', '.'],
     ];
-    assert_eq!(part_2(&puzzle), 9);
+    assert_eq!(part_2(&puzzle, Topology::Bounded), 9);
+}
+
+#[cfg(test)]
+fn to_grid(lines: &[&str]) -> Vec<Vec<char>> {
+    lines.iter().map(|line| line.chars().collect()).collect()
+}
+
+#[test]
+fn check_bounded_degenerate_grids() {
+    assert_eq!(part_1(&[], Topology::Bounded), 0);
+    assert_eq!(part_2(&[], Topology::Bounded), 0);
+    assert_eq!(part_2(&to_grid(&["MS", "AA"]), Topology::Bounded), 0);
+    assert_eq!(part_1(&to_grid(&["X"]), Topology::Torus), 0);
+    //Ragged rows: the missing cells never match
+    let ragged = to_grid(&["XMAS", "M", "AA", "S..S"]);
+    assert_eq!(part_1(&ragged, Topology::Bounded), 2);
+    assert_eq!(
+        part_2(&to_grid(&["M.S", ".A", "M.S"]), Topology::Bounded),
+        1
+    );
+}
+
+#[test]
+fn check_torus() {
+    //Each XMAS only appears once the grid wraps around
+    let puzzle = to_grid(&["ASXM", "....", "....", "...."]);
+    assert_eq!(part_1(&puzzle, Topology::Bounded), 0);
+    assert_eq!(part_1(&puzzle, Topology::Torus), 1);
+    assert_eq!(part_1(&puzzle, Topology::Cylinder), 1);
+
+    let vertical = to_grid(&["A.", "S.", "X.", "M."]);
+    assert_eq!(part_1(&vertical, Topology::Torus), 1);
+    assert_eq!(part_1(&vertical, Topology::Cylinder), 0);
+
+    //A word cannot lap a cycle shorter than itself
+    assert_eq!(part_1(&to_grid(&["XMA"]), Topology::Torus), 0);
+    //On a single row the diagonals of a torus collapse onto the row itself
+    assert_eq!(part_1(&to_grid(&["XMAS"]), Topology::Torus), 3);
+
+    let x_mas = to_grid(&["M.M", "...", "S.S", ".A."]);
+    assert_eq!(part_2(&x_mas, Topology::Bounded), 0);
+    assert_eq!(part_2(&x_mas, Topology::Cylinder), 0);
+    assert_eq!(part_2(&x_mas, Topology::Torus), 1);
+}
+
+#[test]
+fn check_cylinder() {
+    //Diagonal XMAS that crosses the left/right seam
+    let puzzle = to_grid(&["..X.", "...M", "A...", ".S.."]);
+    assert_eq!(part_1(&puzzle, Topology::Bounded), 0);
+    assert_eq!(part_1(&puzzle, Topology::Cylinder), 1);
+    assert_eq!(part_1(&puzzle, Topology::Torus), 1);
+
+    let x_mas = to_grid(&["S.M.", "...A", "S.M."]);
+    assert_eq!(part_2(&x_mas, Topology::Bounded), 0);
+    assert_eq!(part_2(&x_mas, Topology::Cylinder), 1);
+    assert_eq!(part_2(&x_mas, Topology::Torus), 1);
 }
 
 fn main() -> Result<()> {
     let file = File::open("inputs/input04.txt")?;
     let puzzle_input = get_puzzle(file)?;
 
+    let topology = std::env::args()
+        .find_map(|arg| arg.strip_prefix("--topology=").map(str::parse))
+        .unwrap_or(Ok(Topology::Bounded))?;
+
     //Part-1
-    println!("{}", part_1(&puzzle_input));
+    println!("{}", part_1(&puzzle_input, topology));
     //2447
 
     //Part-2
-    println!("{}", part_2(&puzzle_input));
+    println!("{}", part_2(&puzzle_input, topology));
+    //1868
+
+    if std::env::args().any(|arg| arg == "--render") {
+        let matches = find_words(&puzzle_input, &["XMAS"], topology);
+        print!("{}", render_matches(&puzzle_input, &matches, topology));
+    }
 
     Ok(())
 }
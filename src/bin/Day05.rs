@@ -1,61 +1,244 @@
 use anyhow::Result;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
+use thiserror::Error;
 
-fn get_rules_and_updates(file: File) -> Result<(HashSet<(u32, u32)>, Vec<Vec<u32>>)> {
-    let mut reader = BufReader::new(file).lines();
+type Rules = HashSet<(u32, u32)>;
 
-    let mut rules = HashSet::new();
-    let mut updates = Vec::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseMode {
+    //Reject malformed lines and report suspicious ones
+    Strict,
+    //Like the original reader: stop at the first unreadable line and never warn, but still reject
+    //lines that don't parse
+    Lenient,
+}
 
-    while let Some(Ok(line)) = reader.next() {
-        let line = line.trim();
-        if line.is_empty() {
-            break;
+#[derive(Error, Debug)]
+enum ParseError {
+    #[error("Line {line}: malformed rule {text:?}, expected `X|Y`")]
+    MalformedRule { line: usize, text: String },
+    #[error("Line {line}: malformed update {text:?}, expected comma separated pages")]
+    MalformedUpdate { line: usize, text: String },
+    #[error("Line {line}: {source}")]
+    Io { line: usize, source: io::Error },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseWarning {
+    DuplicateRule { line: usize, rule: (u32, u32) },
+    //`rule` contradicts the rule in the opposite direction declared earlier
+    ContradictoryRules { line: usize, rule: (u32, u32) },
+    //An update with an even number of pages has no single middle page
+    EvenLengthUpdate { line: usize, pages: usize },
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::DuplicateRule { line, rule } => {
+                write!(f, "Line {}: duplicate rule {}|{}", line, rule.0, rule.1)
+            }
+            ParseWarning::ContradictoryRules { line, rule } => write!(
+                f,
+                "Line {}: rule {}|{} contradicts {}|{}",
+                line, rule.0, rule.1, rule.1, rule.0
+            ),
+            ParseWarning::EvenLengthUpdate { line, pages } => write!(
+                f,
+                "Line {}: update has {} pages and no single middle page",
+                line, pages
+            ),
         }
+    }
+}
 
-        let parts: Vec<u32> = line
-            .split('|')
-            .map(|s| Ok(s.trim().parse::<u32>()?))
-            .collect::<Result<Vec<_>>>()?;
+#[derive(Debug)]
+struct PrintQueue {
+    rules: Rules,
+    updates: Vec<Vec<u32>>,
+    warnings: Vec<ParseWarning>,
+}
 
-        assert_eq!(parts.len(), 2);
+fn parse_rule(line: &str) -> Option<(u32, u32)> {
+    let (before, after) = line.split_once('|')?;
+    Some((before.trim().parse().ok()?, after.trim().parse().ok()?))
+}
 
-        rules.insert((parts[0], parts[1]));
-    }
+fn parse_update(line: &str) -> Option<Vec<u32>> {
+    line.split(',')
+        .map(|page| page.trim().parse().ok())
+        .collect()
+}
 
-    while let Some(Ok(line)) = reader.next() {
+fn get_rules_and_updates(reader: impl BufRead, mode: ParseMode) -> Result<PrintQueue, ParseError> {
+    let mut rules = HashSet::new();
+    let mut updates = Vec::new();
+    let mut warnings = Vec::new();
+    let mut reading_rules = true;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_number = i + 1;
+        let line = match (line, mode) {
+            (Ok(line), _) => line,
+            (Err(_), ParseMode::Lenient) => break,
+            (Err(source), ParseMode::Strict) => {
+                return Err(ParseError::Io {
+                    line: line_number,
+                    source,
+                });
+            }
+        };
         let line = line.trim();
-        if !line.is_empty() {
-            let update: Vec<u32> = line
-                .split(',')
-                .map(|x| Ok(x.trim().parse()?))
-                .collect::<Result<Vec<_>>>()?;
 
+        if reading_rules {
+            if line.is_empty() {
+                reading_rules = false;
+                continue;
+            }
+
+            let rule = parse_rule(line).ok_or_else(|| ParseError::MalformedRule {
+                line: line_number,
+                text: line.to_string(),
+            })?;
+
+            if !rules.insert(rule) {
+                warnings.push(ParseWarning::DuplicateRule {
+                    line: line_number,
+                    rule,
+                });
+            } else if rules.contains(&(rule.1, rule.0)) {
+                warnings.push(ParseWarning::ContradictoryRules {
+                    line: line_number,
+                    rule,
+                });
+            }
+        } else if !line.is_empty() {
+            let update = parse_update(line).ok_or_else(|| ParseError::MalformedUpdate {
+                line: line_number,
+                text: line.to_string(),
+            })?;
+
+            if update.len() % 2 == 0 {
+                warnings.push(ParseWarning::EvenLengthUpdate {
+                    line: line_number,
+                    pages: update.len(),
+                });
+            }
             updates.push(update);
         }
     }
 
-    Ok((rules, updates))
+    if mode == ParseMode::Lenient {
+        warnings.clear();
+    }
+
+    Ok(PrintQueue {
+        rules,
+        updates,
+        warnings,
+    })
 }
 
-fn is_valid_update(update: &Vec<u32>, rules: &HashSet<(u32, u32)>) -> bool {
+#[test]
+fn check_parser() {
+    let input = "1|2\n2|1\n1|2\n\n1,2,3\n1,2\n";
+    let queue = get_rules_and_updates(input.as_bytes(), ParseMode::Strict).unwrap();
+    assert_eq!(queue.rules.len(), 2);
+    assert_eq!(queue.updates, vec![vec![1, 2, 3], vec![1, 2]]);
+    assert_eq!(
+        queue.warnings,
+        vec![
+            ParseWarning::ContradictoryRules {
+                line: 2,
+                rule: (2, 1)
+            },
+            ParseWarning::DuplicateRule {
+                line: 3,
+                rule: (1, 2)
+            },
+            ParseWarning::EvenLengthUpdate { line: 6, pages: 2 },
+        ]
+    );
+
+    let bad_rule = "1|2\n3-4\n\n1,2,3\n";
+    assert_eq!(
+        get_rules_and_updates(bad_rule.as_bytes(), ParseMode::Strict)
+            .unwrap_err()
+            .to_string(),
+        "Line 2: malformed rule \"3-4\", expected `X|Y`"
+    );
+    let bad_update = "1|2\n\n1,2,3\n1,,3\n";
+    assert!(matches!(
+        get_rules_and_updates(bad_update.as_bytes(), ParseMode::Strict),
+        Err(ParseError::MalformedUpdate { line: 4, .. })
+    ));
+
+    //Lenient mode only drops the warnings, malformed lines are still errors as they always were
+    let lenient = get_rules_and_updates(input.as_bytes(), ParseMode::Lenient).unwrap();
+    assert_eq!(lenient.rules, queue.rules);
+    assert_eq!(lenient.updates, queue.updates);
+    assert!(lenient.warnings.is_empty());
+    assert!(matches!(
+        get_rules_and_updates("1|2\nx|4\n\n1,2,3".as_bytes(), ParseMode::Lenient),
+        Err(ParseError::MalformedRule { line: 2, .. })
+    ));
+    assert!(matches!(
+        get_rules_and_updates(bad_update.as_bytes(), ParseMode::Lenient),
+        Err(ParseError::MalformedUpdate { line: 4, .. })
+    ));
+}
+
+///A rule `before|after` broken by `after` being printed earlier in the update than `before`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Violation {
+    before: u32,
+    after: u32,
+    before_position: usize,
+    after_position: usize,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}|{} is violated: {} at position {} comes before {} at position {}",
+            self.before,
+            self.after,
+            self.after,
+            self.after_position,
+            self.before,
+            self.before_position
+        )
+    }
+}
+
+fn violations(update: &[u32], rules: &Rules) -> Vec<Violation> {
+    let mut violations = Vec::new();
     for i in 0..update.len() {
         for j in i + 1..update.len() {
-            if !rules.contains(&(update[i], update[j])) {
-                return false;
+            if rules.contains(&(update[j], update[i])) {
+                violations.push(Violation {
+                    before: update[j],
+                    after: update[i],
+                    before_position: j,
+                    after_position: i,
+                });
             }
         }
     }
 
-    true
+    violations
 }
-fn part_1(rules: &HashSet<(u32, u32)>, updates: &Vec<Vec<u32>>) -> u32 {
+
+fn part_1(rules: &Rules, updates: &[Vec<u32>]) -> u32 {
     updates
         .iter()
         .filter_map(|update| {
-            if is_valid_update(update, rules) {
+            if violations(update, rules).is_empty() {
                 update.get(update.len() / 2).copied()
             } else {
                 None
@@ -64,42 +247,554 @@ fn part_1(rules: &HashSet<(u32, u32)>, updates: &Vec<Vec<u32>>) -> u32 {
         .sum()
 }
 
-fn part_2(rules: &HashSet<(u32, u32)>, updates: &Vec<Vec<u32>>) -> u32 {
-    fn comparator(a: &u32, b: &u32, rules: &HashSet<(u32, u32)>) -> std::cmp::Ordering {
-        if rules.contains(&(*a, *b)) {
-            std::cmp::Ordering::Less
-        } else if rules.contains(&(*a, *b)) {
-            std::cmp::Ordering::Greater
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Ordering rules form a cycle through pages {pages:?}")]
+struct RuleCycle {
+    pages: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct CorrectedUpdate {
+    pages: Vec<u32>,
+    //Set when the rules leave some pair of pages unordered, so other orderings are valid too
+    ambiguous: bool,
+}
+
+///Takes `page` out of the update and puts it back so that it ends up at index `to`. Both positions
+///are in the update as it is after the earlier moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PageMove {
+    page: u32,
+    from: usize,
+    to: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ValidationReport {
+    violations: Vec<Violation>,
+    moves: Vec<PageMove>,
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.violations.is_empty() {
+            return writeln!(f, "Update is in the right order");
+        }
+        for violation in &self.violations {
+            writeln!(f, "{}", violation)?;
+        }
+        for page_move in &self.moves {
+            writeln!(
+                f,
+                "Move {} from position {} to position {}",
+                page_move.page, page_move.from, page_move.to
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+///Orders the pages of an update using only the rules between pages that appear in it
+struct PageOrdering<'a> {
+    rules: &'a Rules,
+}
+
+impl<'a> PageOrdering<'a> {
+    fn new(rules: &'a Rules) -> Self {
+        PageOrdering { rules }
+    }
+
+    ///Edges between positions of the update for every rule whose two pages are both present
+    fn subgraph(&self, update: &[u32]) -> Vec<Vec<usize>> {
+        (0..update.len())
+            .map(|i| {
+                (0..update.len())
+                    .filter(|&j| i != j && self.rules.contains(&(update[i], update[j])))
+                    .collect()
+            })
+            .collect()
+    }
+
+    ///Topological sort of the induced subgraph. Among the pages that are free to go next, the one
+    ///that came first in the update is picked, so unconstrained pages keep their relative order.
+    fn order(&self, update: &[u32]) -> Result<CorrectedUpdate, RuleCycle> {
+        let edges = self.subgraph(update);
+        let mut in_degree = vec![0; update.len()];
+        for &j in edges.iter().flatten() {
+            in_degree[j] += 1;
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..update.len())
+            .filter(|&i| in_degree[i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut pages = Vec::with_capacity(update.len());
+        let mut ambiguous = false;
+
+        while let Some(Reverse(i)) = ready.pop() {
+            ambiguous |= !ready.is_empty();
+            pages.push(update[i]);
+            for &j in &edges[i] {
+                in_degree[j] -= 1;
+                if in_degree[j] == 0 {
+                    ready.push(Reverse(j));
+                }
+            }
+        }
+
+        if pages.len() < update.len() {
+            return Err(RuleCycle {
+                pages: Self::find_cycle(update, &edges, &in_degree),
+            });
+        }
+
+        Ok(CorrectedUpdate { pages, ambiguous })
+    }
+
+    ///Lists the broken rules and the fewest pages that have to move to reach the corrected order.
+    ///The pages that stay put are a longest subsequence of the update that is already in corrected
+    ///order, so the moves are minimal for that order, which is the only one unless it is ambiguous.
+    fn validate(&self, update: &[u32]) -> Result<ValidationReport, RuleCycle> {
+        let violations = violations(update, self.rules);
+        if violations.is_empty() {
+            return Ok(ValidationReport {
+                violations,
+                moves: Vec::new(),
+            });
+        }
+
+        let corrected = self.order(update)?.pages;
+        let mut targets: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (to, &page) in corrected.iter().enumerate().rev() {
+            targets.entry(page).or_default().push(to);
+        }
+        let target: Vec<usize> = update
+            .iter()
+            .map(|page| targets.get_mut(page).and_then(Vec::pop).unwrap())
+            .collect();
+
+        //Pages that are kept or already moved are in corrected order among themselves, so each
+        //moved page goes straight after the placed page that precedes it
+        let kept = longest_increasing_subsequence(&target);
+        let mut placed: Vec<bool> = (0..update.len()).map(|i| kept.contains(&i)).collect();
+        let mut movers: Vec<usize> = (0..update.len()).filter(|&i| !placed[i]).collect();
+        movers.sort_unstable_by_key(|&i| target[i]);
+
+        let mut current: Vec<usize> = (0..update.len()).collect();
+        let mut moves = Vec::new();
+        for mover in movers {
+            let from = current.iter().position(|&i| i == mover).unwrap();
+            current.remove(from);
+            let to = current
+                .iter()
+                .rposition(|&i| placed[i] && target[i] < target[mover])
+                .map_or(0, |position| position + 1);
+            current.insert(to, mover);
+            placed[mover] = true;
+
+            if from != to {
+                moves.push(PageMove {
+                    page: update[mover],
+                    from,
+                    to,
+                });
+            }
+        }
+
+        Ok(ValidationReport { violations, moves })
+    }
+
+    ///Every page left with a positive in-degree after the sort has a predecessor that is also left,
+    ///so walking backwards along those edges must eventually revisit a page
+    fn find_cycle(update: &[u32], edges: &[Vec<usize>], in_degree: &[usize]) -> Vec<u32> {
+        let remaining = |i: usize| in_degree[i] > 0;
+        let predecessor = |j: usize| {
+            (0..update.len())
+                .find(|&i| remaining(i) && edges[i].contains(&j))
+                .unwrap()
+        };
+
+        let mut seen = vec![None; update.len()];
+        let mut path = Vec::new();
+        let mut current = (0..update.len()).find(|&i| remaining(i)).unwrap();
+        while seen[current].is_none() {
+            seen[current] = Some(path.len());
+            path.push(current);
+            current = predecessor(current);
+        }
+
+        //The walk went against the edges, so reverse it to list the cycle in rule order
+        let mut cycle: Vec<u32> = path[seen[current].unwrap()..]
+            .iter()
+            .map(|&i| update[i])
+            .collect();
+        cycle.reverse();
+        cycle
+    }
+}
+
+///A rule edge `from|to` and whether the exported update breaks it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RuleEdge {
+    from: u32,
+    to: u32,
+    violated: bool,
+}
+
+impl PageOrdering<'_> {
+    ///Pages and rules in ascending order, restricted to the pages of `update` if one is given
+    fn graph(&self, update: Option<&[u32]>) -> (Vec<u32>, Vec<RuleEdge>) {
+        let broken: HashSet<(u32, u32)> = update
+            .map(|update| {
+                violations(update, self.rules)
+                    .iter()
+                    .map(|violation| (violation.before, violation.after))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let included = |page: &u32| update.is_none_or(|update| update.contains(page));
+
+        let mut edges: Vec<RuleEdge> = self
+            .rules
+            .iter()
+            .filter(|(from, to)| included(from) && included(to))
+            .map(|&(from, to)| RuleEdge {
+                from,
+                to,
+                violated: broken.contains(&(from, to)),
+            })
+            .collect();
+        edges.sort_by_key(|edge| (edge.from, edge.to));
+
+        let mut pages: Vec<u32> = match update {
+            Some(update) => update.to_vec(),
+            None => self
+                .rules
+                .iter()
+                .flat_map(|&(from, to)| [from, to])
+                .collect(),
+        };
+        pages.sort_unstable();
+        pages.dedup();
+
+        (pages, edges)
+    }
+
+    ///Graphviz digraph of the rules with violated edges drawn in red
+    fn to_dot(&self, update: Option<&[u32]>) -> String {
+        let (pages, edges) = self.graph(update);
+        let mut dot = String::from("digraph rules {\n");
+
+        for page in pages {
+            dot.push_str(&format!("    {};\n", page));
+        }
+        for edge in edges {
+            let style = if edge.violated { " [color=red]" } else { "" };
+            dot.push_str(&format!("    {} -> {}{};\n", edge.from, edge.to, style));
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    fn to_json(&self, update: Option<&[u32]>) -> String {
+        let (pages, edges) = self.graph(update);
+        let nodes = pages
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let edges = edges
+            .iter()
+            .map(|edge| {
+                format!(
+                    "{{\"from\":{},\"to\":{},\"violated\":{}}}",
+                    edge.from, edge.to, edge.violated
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!("{{\"nodes\":[{}],\"edges\":[{}]}}", nodes, edges)
+    }
+}
+
+#[test]
+fn check_export() {
+    let rules: Rules = [(1, 2), (2, 3), (1, 3), (4, 1)].into_iter().collect();
+    let ordering = PageOrdering::new(&rules);
+
+    assert_eq!(
+        ordering.to_dot(None),
+        "digraph rules {\n    1;\n    2;\n    3;\n    4;\n    1 -> 2;\n    1 -> 3;\n    2 -> 3;\n    4 -> 1;\n}\n"
+    );
+    assert_eq!(
+        ordering.to_dot(Some(&[3, 1, 2])),
+        "digraph rules {\n    1;\n    2;\n    3;\n    1 -> 2;\n    1 -> 3 [color=red];\n    2 -> 3 [color=red];\n}\n"
+    );
+    assert_eq!(
+        ordering.to_json(Some(&[1, 4])),
+        r#"{"nodes":[1,4],"edges":[{"from":4,"to":1,"violated":true}]}"#
+    );
+}
+
+///Indices of one longest strictly increasing subsequence of `values`
+fn longest_increasing_subsequence(values: &[usize]) -> HashSet<usize> {
+    //tails[k] is the index ending the smallest-valued increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+
+    for (i, &value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&t| values[t] < value);
+        if k > 0 {
+            previous[i] = Some(tails[k - 1]);
+        }
+        if k == tails.len() {
+            tails.push(i);
         } else {
-            std::cmp::Ordering::Equal
+            tails[k] = i;
         }
     }
+
+    let mut subsequence = HashSet::new();
+    let mut current = tails.last().copied();
+    while let Some(i) = current {
+        subsequence.insert(i);
+        current = previous[i];
+    }
+
+    subsequence
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+#[test]
+fn check_page_ordering() {
+    let PrintQueue { rules, updates, .. } =
+        get_rules_and_updates(EXAMPLE.as_bytes(), ParseMode::Strict).unwrap();
+    let ordering = PageOrdering::new(&rules);
+
+    assert_eq!(
+        ordering.order(&updates[3]).unwrap(),
+        CorrectedUpdate {
+            pages: vec![97, 75, 47, 61, 53],
+            ambiguous: false
+        }
+    );
+    assert_eq!(
+        ordering.order(&updates[5]).unwrap().pages,
+        vec![97, 75, 47, 29, 13]
+    );
+
+    let rules: Rules = [(1, 2), (2, 3), (3, 1), (1, 4)].into_iter().collect();
+    let ordering = PageOrdering::new(&rules);
+    assert_eq!(
+        ordering.order(&[4, 2, 1, 3]),
+        Err(RuleCycle {
+            pages: vec![2, 3, 1]
+        })
+    );
+    //Nothing relates 5 to 1 or 2
+    assert_eq!(
+        ordering.order(&[5, 2, 1]).unwrap(),
+        CorrectedUpdate {
+            pages: vec![5, 1, 2],
+            ambiguous: true
+        }
+    );
+}
+
+#[test]
+fn check_validate() {
+    let PrintQueue { rules, updates, .. } =
+        get_rules_and_updates(EXAMPLE.as_bytes(), ParseMode::Strict).unwrap();
+    let ordering = PageOrdering::new(&rules);
+
+    assert_eq!(
+        ordering.validate(&updates[0]).unwrap(),
+        ValidationReport {
+            violations: Vec::new(),
+            moves: Vec::new()
+        }
+    );
+
+    let report = ordering.validate(&updates[3]).unwrap();
+    assert_eq!(
+        report.violations,
+        vec![Violation {
+            before: 97,
+            after: 75,
+            before_position: 1,
+            after_position: 0
+        }]
+    );
+    assert_eq!(
+        report.moves,
+        vec![PageMove {
+            page: 75,
+            from: 0,
+            to: 1
+        }]
+    );
+
+    //61,13,29 -> 61,29,13 and 97,13,75,29,47 -> 97,75,47,29,13
+    assert_eq!(ordering.validate(&updates[4]).unwrap().moves.len(), 1);
+    let report = ordering.validate(&updates[5]).unwrap();
+    assert_eq!(report.violations.len(), 4);
+    assert_eq!(
+        report.moves,
+        vec![
+            PageMove {
+                page: 29,
+                from: 3,
+                to: 4
+            },
+            PageMove {
+                page: 13,
+                from: 1,
+                to: 4
+            }
+        ]
+    );
+    assert_eq!(
+        report.violations[0].to_string(),
+        "75|13 is violated: 13 at position 1 comes before 75 at position 2"
+    );
+
+    //Applying the moves in order gives the corrected update
+    let rules: Rules = [(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)].into();
+    let ordering = PageOrdering::new(&rules);
+    let updates = [
+        vec![3, 4, 1, 2],
+        vec![4, 3, 2, 1],
+        vec![2, 4, 1, 3],
+        vec![1, 4, 3, 2],
+    ];
+    for update in &updates {
+        let mut moved = update.clone();
+        for page_move in ordering.validate(update).unwrap().moves {
+            assert_ne!(page_move.from, page_move.to);
+            assert_eq!(moved.remove(page_move.from), page_move.page);
+            moved.insert(page_move.to, page_move.page);
+        }
+        assert_eq!(moved, ordering.order(update).unwrap().pages, "{:?}", update);
+    }
+}
+
+fn part_2(rules: &Rules, updates: &[Vec<u32>]) -> Result<u32> {
+    let ordering = PageOrdering::new(rules);
+
     updates
         .iter()
-        .filter_map(|update| {
-            if !is_valid_update(update, rules) {
-                let mut update = update.clone();
-                let mid = update.len() / 2;
-                update.select_nth_unstable_by(mid, |a, b| comparator(a, b, rules));
-                Some(update[mid])
-            } else {
-                None
-            }
+        .filter(|update| !violations(update, rules).is_empty())
+        .map(|update| {
+            let corrected = ordering.order(update)?;
+            Ok(corrected.pages[corrected.pages.len() / 2])
         })
         .sum()
 }
 
+#[test]
+fn check_parts() {
+    let PrintQueue { rules, updates, .. } =
+        get_rules_and_updates(EXAMPLE.as_bytes(), ParseMode::Strict).unwrap();
+
+    assert_eq!(part_1(&rules, &updates), 143);
+    assert_eq!(part_2(&rules, &updates).unwrap(), 123);
+}
+
 fn main() -> Result<()> {
     let file = File::open("inputs/input05.txt")?;
 
-    let (rules, updates) = get_rules_and_updates(file)?;
+    let mode = if std::env::args().any(|arg| arg == "--lenient") {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    };
+    let PrintQueue {
+        rules,
+        updates,
+        warnings,
+    } = get_rules_and_updates(BufReader::new(file), mode)?;
+    for warning in &warnings {
+        eprintln!("{}", warning);
+    }
 
     //Part-1
     println!("{}", part_1(&rules, &updates));
     //6498
 
     //Part-2
-    println!("{}", part_2(&rules, &updates));
+    println!("{}", part_2(&rules, &updates)?);
     //5017
+
+    let ordering = PageOrdering::new(&rules);
+
+    //`--dot` or `--json`, optionally with `--update=N` to export only the N-th update's pages
+    let export_update = std::env::args()
+        .find_map(|arg| arg.strip_prefix("--update=").map(str::parse::<usize>))
+        .transpose()?
+        .map(|n| {
+            n.checked_sub(1)
+                .and_then(|i| updates.get(i))
+                .map(Vec::as_slice)
+                .ok_or_else(|| anyhow::anyhow!("There is no update {}", n))
+        })
+        .transpose()?;
+    for arg in std::env::args() {
+        match arg.as_str() {
+            "--dot" => print!("{}", ordering.to_dot(export_update)),
+            "--json" => println!("{}", ordering.to_json(export_update)),
+            _ => {}
+        }
+    }
+
+    //`--explain` reports rule cycles, ambiguous orderings and why each invalid update was rejected
+    if std::env::args().any(|arg| arg == "--explain") {
+        for (i, update) in updates.iter().enumerate() {
+            match ordering.validate(update) {
+                Ok(report) => {
+                    if !report.violations.is_empty() {
+                        println!("Update {}:\n{}", i + 1, report);
+                    }
+                    if ordering.order(update)?.ambiguous {
+                        println!("Update {} has more than one valid ordering", i + 1);
+                    }
+                }
+                Err(cycle) => println!("Update {}: {}", i + 1, cycle),
+            }
+        }
+    }
+
     Ok(())
 }
@@ -1,37 +1,153 @@
 use anyhow::Result;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
+use std::num::NonZero;
+use std::thread;
+
+//Clockwise in steps of 45 degrees, so turning right is moving two places along
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+//A guard facing `GUARD_SYMBOLS[i]` faces `DIRECTIONS[2 * i]`
+const GUARD_SYMBOLS: [char; 4] = ['^', '>', 'v', '<'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TurnPolicy {
+    Right,
+    Left,
+    Reverse,
+    //Right on the first turn, left on the second, and so on
+    Alternate,
+}
+
+impl std::str::FromStr for TurnPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(TurnPolicy::Right),
+            "left" => Ok(TurnPolicy::Left),
+            "reverse" => Ok(TurnPolicy::Reverse),
+            "alternate" => Ok(TurnPolicy::Alternate),
+            _ => Err(anyhow::anyhow!("Invalid TurnPolicy: {}", s)),
+        }
+    }
+}
+
+impl TurnPolicy {
+    ///Heading after running into an obstacle, `phase` being the number of earlier turns modulo
+    ///`Patrol::phases`
+    fn turn(self, direction: usize, phase: usize) -> usize {
+        let eighths = match self {
+            TurnPolicy::Right => 2,
+            TurnPolicy::Left => 6,
+            TurnPolicy::Reverse => 4,
+            TurnPolicy::Alternate if phase == 0 => 2,
+            TurnPolicy::Alternate => 6,
+        };
+
+        (direction + eighths) % DIRECTIONS.len()
+    }
+}
+
+///The rules every guard in the lab follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Patrol {
+    turn: TurnPolicy,
+    //Guards move diagonally, starting 45 degrees clockwise of the way they face
+    diagonal: bool,
+}
+
+impl Default for Patrol {
+    fn default() -> Self {
+        Patrol {
+            turn: TurnPolicy::Right,
+            diagonal: false,
+        }
+    }
+}
+
+impl Patrol {
+    fn start(self, guard: &Guard) -> Pose {
+        Pose {
+            position: guard.position,
+            direction: guard.direction + self.diagonal as usize,
+            phase: 0,
+        }
+    }
+
+    ///How many turn counts a guard has to tell apart, which is only more than one when the next
+    ///turn depends on the previous ones
+    fn phases(self) -> usize {
+        match self.turn {
+            TurnPolicy::Alternate => 2,
+            TurnPolicy::Right | TurnPolicy::Left | TurnPolicy::Reverse => 1,
+        }
+    }
+}
+
+///Everything that decides where a guard goes next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Pose {
+    position: (usize, usize),
+    direction: usize,
+    phase: usize,
+}
 
 #[derive(Debug)]
 enum CellType {
-    Guard,
+    //Index into `DIRECTIONS` of the way the guard is facing
+    Guard(usize),
     Obstacle,
     Empty,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Guard {
+    position: (usize, usize),
+    direction: usize,
+}
+
+///Cells a guard covers before walking out of the lab, or before it starts repeating itself
+#[derive(Debug)]
+struct Walk {
+    visited: HashSet<(usize, usize)>,
+    loops: bool,
+}
+
 #[derive(Debug)]
 struct Lab {
     rows: usize,
     columns: usize,
     lab: Vec<Vec<CellType>>,
+    patrol: Patrol,
 }
 
 impl Lab {
-    fn get_matrix(file: File) -> Result<Self> {
-        let reader = io::BufReader::new(file);
-
+    fn get_matrix(reader: impl BufRead) -> Result<Self> {
         let lab = reader
             .lines()
             .map(|line| {
                 line?
                     .chars()
                     .map(|c| match c {
-                        '^' => Ok(CellType::Guard),
                         '#' => Ok(CellType::Obstacle),
                         '.' => Ok(CellType::Empty),
-                        _ => Err(anyhow::anyhow!("Unexpected char type {}", c)),
+                        _ => GUARD_SYMBOLS
+                            .iter()
+                            .position(|&symbol| symbol == c)
+                            .map(|i| CellType::Guard(2 * i))
+                            .ok_or_else(|| anyhow::anyhow!("Unexpected char type {}", c)),
                     })
                     .collect::<Result<Vec<CellType>>>()
             })
@@ -40,113 +156,134 @@ impl Lab {
         let rows = lab.len();
         let columns = lab[0].len();
 
-        Ok(Lab { rows, columns, lab })
+        Ok(Lab {
+            rows,
+            columns,
+            lab,
+            patrol: Patrol::default(),
+        })
     }
 
-    fn guard(&self) -> Result<(usize, usize)> {
+    ///Every guard in reading order. Each one is analysed on its own, the others being just floor.
+    fn guards(&self) -> Result<Vec<Guard>> {
+        let mut guards = Vec::new();
         for i in 0..self.rows {
             for j in 0..self.columns {
-                if let CellType::Guard = self.lab[i][j] {
-                    return Ok((i, j));
+                if let CellType::Guard(direction) = self.lab[i][j] {
+                    guards.push(Guard {
+                        position: (i, j),
+                        direction,
+                    });
                 }
             }
         }
 
-        Err(anyhow::anyhow!("No guard found in the lab"))
+        if guards.is_empty() {
+            return Err(anyhow::anyhow!("No guard found in the lab"));
+        }
+
+        Ok(guards)
     }
 
     fn is_out_of_free(&self, x: isize, y: isize) -> bool {
         x < 0 || x >= self.rows as isize || y < 0 || y >= self.columns as isize
     }
 
-    fn rotate_right(&self, direction: (isize, isize)) -> (isize, isize) {
-        match direction {
-            (0, 1) => (1, 0),
-            (1, 0) => (0, -1),
-            (0, -1) => (-1, 0),
-            (-1, 0) => (0, 1),
-            _ => unreachable!(),
-        }
+    ///Size of a buffer indexed by `state`
+    fn states(&self) -> usize {
+        self.rows * self.columns * DIRECTIONS.len() * 2
     }
 
-    ///Part-1
-    fn count_guard_walk(&self) -> Result<(HashSet<(usize, usize)>, usize)> {
-        let (mut x, mut y) = self.guard()?;
-        let mut direction = (-1, 0); // Start moving up
-        let mut vis = HashSet::new();
-        vis.insert((x, y));
+    fn state(&self, pose: &Pose) -> usize {
+        let (x, y) = pose.position;
+        ((x * self.columns + y) * DIRECTIONS.len() + pose.direction) * 2 + pose.phase
+    }
 
-        loop {
-            let (dx, dy) = direction;
-            let (new_x, new_y) = (x as isize + dx, y as isize + dy);
+    fn turned(&self, pose: Pose) -> Pose {
+        Pose {
+            direction: self.patrol.turn.turn(pose.direction, pose.phase),
+            phase: (pose.phase + 1) % self.patrol.phases(),
+            ..pose
+        }
+    }
 
-            if self.is_out_of_free(new_x, new_y) {
-                break;
-            }
+    ///The pose after one move or turn, or `None` once the guard walks out of the lab
+    fn advance(&self, pose: Pose, obstacle: Option<(usize, usize)>) -> Option<Pose> {
+        let (x, y) = pose.position;
+        let (dx, dy) = DIRECTIONS[pose.direction];
+        let (new_x, new_y) = (x as isize + dx, y as isize + dy);
 
-            match self.lab[new_x as usize][new_y as usize] {
-                CellType::Obstacle => {
-                    // Rotate right until a valid direction is found
-                    direction = self.rotate_right(direction);
-                }
-                _ => {
-                    x = new_x as usize;
-                    y = new_y as usize;
-                    vis.insert((x, y));
-                }
-            }
+        if self.is_out_of_free(new_x, new_y) {
+            return None;
+        }
+
+        let (new_x, new_y) = (new_x as usize, new_y as usize);
+        if obstacle == Some((new_x, new_y)) {
+            return Some(self.turned(pose));
         }
 
-        let cells_covered = vis.len();
-        Ok((vis, cells_covered))
+        match self.lab[new_x][new_y] {
+            CellType::Obstacle => Some(self.turned(pose)),
+            _ => Some(Pose {
+                position: (new_x, new_y),
+                ..pose
+            }),
+        }
     }
 
-    fn is_loop(&self) -> Result<bool> {
-        let (mut x, mut y) = self.guard()?;
-        let mut direction = (-1, 0);
-        let mut vis = HashSet::new();
+    fn walk(&self, guard: &Guard) -> Walk {
+        let mut pose = self.patrol.start(guard);
+        let mut visited = HashSet::from([pose.position]);
+        let mut states = HashSet::new();
 
-        loop {
-            if !vis.insert(((x, y), direction)) {
-                return Ok(true);
+        while let Some(next) = self.advance(pose, None) {
+            if !states.insert(pose) {
+                return Walk {
+                    visited,
+                    loops: true,
+                };
             }
 
-            let (dx, dy) = direction;
-            let (new_x, new_y) = (x as isize + dx, y as isize + dy);
-
-            if self.is_out_of_free(new_x, new_y) {
-                break;
-            }
+            visited.insert(next.position);
+            pose = next;
+        }
 
-            match self.lab[new_x as usize][new_y as usize] {
-                CellType::Obstacle => {
-                    // Rotate right until a valid direction is found
-                    direction = self.rotate_right(direction);
-                }
-                _ => {
-                    x = new_x as usize;
-                    y = new_y as usize;
-                }
-            }
+        Walk {
+            visited,
+            loops: false,
         }
+    }
 
-        Ok(false)
+    ///Part-1, for every guard at once
+    fn coverage(&self) -> Result<Coverage> {
+        let walks: Vec<(Guard, Walk)> = self
+            .guards()?
+            .into_iter()
+            .map(|guard| (guard, self.walk(&guard)))
+            .collect();
+        let combined = walks
+            .iter()
+            .flat_map(|(_, walk)| walk.visited.iter().copied())
+            .collect();
+
+        Ok(Coverage { walks, combined })
     }
-    ///Part-2
-    fn count_multiverses_with_loops(&mut self) -> Result<usize> {
+
+    ///Part-2, by re-walking the whole lab for every candidate. Kept to check the jump table version
+    #[cfg(test)]
+    fn count_multiverses_with_loops_brute_force(&mut self, guard: &Guard) -> Result<usize> {
         //Check only the cells in the guard's path since the other cells cannot be visited
-        let path = self.count_guard_walk()?.0;
-        let guard = self.guard()?;
+        let path = self.walk(guard).visited;
         let mut count = 0;
 
         for &(i, j) in &path {
-            if (i, j) == guard {
+            if (i, j) == guard.position {
                 continue;
             }
 
             self.lab[i][j] = CellType::Obstacle;
 
-            if self.is_loop()? {
+            if self.walk(guard).loops {
                 count += 1;
             }
 
@@ -157,17 +294,598 @@ impl Lab {
     }
 }
 
+///How far the guard gets from a cell in a heading before an obstacle stops it or it walks out
+#[derive(Debug, Clone, Copy)]
+struct Reach {
+    steps: usize,
+    exits: bool,
+}
+
+///The guard only ever needs to stop where it turns, so instead of walking cell by cell every trial
+///jumps straight to the next obstacle using this table
+struct JumpTable {
+    columns: usize,
+    reach: Vec<[Reach; DIRECTIONS.len()]>,
+}
+
+impl JumpTable {
+    fn new(lab: &Lab) -> Self {
+        let mut reach = vec![
+            [Reach {
+                steps: 0,
+                exits: true
+            }; DIRECTIONS.len()];
+            lab.rows * lab.columns
+        ];
+
+        for (d, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+            //Visit cells so that the neighbour in the heading is always filled in first
+            let rows: Vec<usize> = if dx > 0 {
+                (0..lab.rows).rev().collect()
+            } else {
+                (0..lab.rows).collect()
+            };
+            let columns: Vec<usize> = if dy > 0 {
+                (0..lab.columns).rev().collect()
+            } else {
+                (0..lab.columns).collect()
+            };
+
+            for &x in &rows {
+                for &y in &columns {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    reach[x * lab.columns + y][d] = if lab.is_out_of_free(nx, ny) {
+                        Reach {
+                            steps: 0,
+                            exits: true,
+                        }
+                    } else if let CellType::Obstacle = lab.lab[nx as usize][ny as usize] {
+                        Reach {
+                            steps: 0,
+                            exits: false,
+                        }
+                    } else {
+                        let next = reach[nx as usize * lab.columns + ny as usize][d];
+                        Reach {
+                            steps: next.steps + 1,
+                            exits: next.exits,
+                        }
+                    };
+                }
+            }
+        }
+
+        JumpTable {
+            columns: lab.columns,
+            reach,
+        }
+    }
+
+    ///Reach from `(x, y)` as if there were one more obstacle at `obstacle`
+    fn reach_with(&self, (x, y): (usize, usize), d: usize, obstacle: (usize, usize)) -> Reach {
+        let reach = self.reach[x * self.columns + y][d];
+        let (dx, dy) = DIRECTIONS[d];
+        let (ox, oy) = (
+            obstacle.0 as isize - x as isize,
+            obstacle.1 as isize - y as isize,
+        );
+
+        //Steps to the extra obstacle if it lies straight ahead
+        let distance = if dx != 0 { ox * dx } else { oy * dy };
+        if distance >= 1
+            && (ox, oy) == (distance * dx, distance * dy)
+            && (reach.exits || distance as usize <= reach.steps)
+        {
+            return Reach {
+                steps: distance as usize - 1,
+                exits: false,
+            };
+        }
+
+        reach
+    }
+}
+
+///An obstacle to try, with the pose the guard had just before first stepping onto that cell in
+///the original walk. The obstacle only changes the walk from that point on.
+#[derive(Debug, Clone, Copy)]
+struct Trial {
+    obstacle: (usize, usize),
+    start: Pose,
+}
+
+impl Lab {
+    ///One trial for every cell of the original walk except the guard's own. A guard that is
+    ///already walking in a loop has nothing to try.
+    fn trials(&self, guard: &Guard) -> Vec<Trial> {
+        let mut pose = self.patrol.start(guard);
+        let mut seen = vec![false; self.rows * self.columns];
+        let mut states = vec![false; self.states()];
+        let (x, y) = pose.position;
+        seen[x * self.columns + y] = true;
+        let mut trials = Vec::new();
+
+        while let Some(next) = self.advance(pose, None) {
+            let state = self.state(&pose);
+            if states[state] {
+                return Vec::new();
+            }
+            states[state] = true;
+
+            let (x, y) = next.position;
+            if !seen[x * self.columns + y] {
+                seen[x * self.columns + y] = true;
+                trials.push(Trial {
+                    obstacle: next.position,
+                    start: pose,
+                });
+            }
+            pose = next;
+        }
+
+        trials
+    }
+
+    ///Jumps from turn to turn. `seen` holds the stamp of the trial that last visited each
+    ///turning pose, so one buffer serves every trial on a thread.
+    fn loops_with_obstacle(
+        &self,
+        table: &JumpTable,
+        trial: &Trial,
+        seen: &mut [usize],
+        stamp: usize,
+    ) -> bool {
+        let mut pose = trial.start;
+
+        loop {
+            let reach = table.reach_with(pose.position, pose.direction, trial.obstacle);
+            if reach.exits {
+                return false;
+            }
+
+            let (x, y) = pose.position;
+            let (dx, dy) = DIRECTIONS[pose.direction];
+            pose.position = (
+                (x as isize + dx * reach.steps as isize) as usize,
+                (y as isize + dy * reach.steps as isize) as usize,
+            );
+
+            let state = self.state(&pose);
+            if seen[state] == stamp {
+                return true;
+            }
+            seen[state] = stamp;
+            pose = self.turned(pose);
+        }
+    }
+
+    ///The trials whose obstacle traps the guard, tried in parallel
+    fn looping_trials(&self, guard: &Guard, threads: usize) -> Vec<Trial> {
+        let table = JumpTable::new(self);
+        let trials = self.trials(guard);
+        let chunk_size = trials.len().div_ceil(threads.max(1)).max(1);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = trials
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let table = &table;
+                    scope.spawn(move || {
+                        //Stamps start at 1 so that the zeroed buffer counts as unvisited
+                        let mut seen = vec![0; self.states()];
+                        chunk
+                            .iter()
+                            .enumerate()
+                            .filter(|&(i, trial)| {
+                                self.loops_with_obstacle(table, trial, &mut seen, i + 1)
+                            })
+                            .map(|(_, trial)| *trial)
+                            .collect::<Vec<Trial>>()
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        })
+    }
+
+    ///Part-2
+    fn count_multiverses_with_loops(&self, guard: &Guard, threads: usize) -> usize {
+        self.looping_trials(guard, threads).len()
+    }
+
+    ///Every obstacle position that traps the guard, with the loop it ends up walking
+    fn trapping_obstacles(&self, guard: &Guard, threads: usize) -> Vec<GuardLoop> {
+        let mut loops: Vec<GuardLoop> = self
+            .looping_trials(guard, threads)
+            .iter()
+            .map(|trial| self.guard_loop(trial))
+            .collect();
+        loops.sort_by_key(|guard_loop| guard_loop.obstacle);
+
+        loops
+    }
+
+    ///Walks cell by cell from the trial's start until a pose repeats. The first pose to repeat is
+    ///where the guard enters the cycle.
+    fn guard_loop(&self, trial: &Trial) -> GuardLoop {
+        let mut pose = trial.start;
+        let mut path = Vec::new();
+        let mut index = HashMap::new();
+
+        let entry = loop {
+            if let Some(&i) = index.get(&pose) {
+                break i;
+            }
+            index.insert(pose, path.len());
+            path.push(pose);
+
+            //Only ever called for trials that loop, so the guard never walks out
+            pose = self.advance(pose, Some(trial.obstacle)).unwrap();
+        };
+
+        let cycle = path.split_off(entry);
+        let length = cycle
+            .iter()
+            .zip(cycle.iter().cycle().skip(1))
+            .filter(|(from, to)| from.position != to.position)
+            .count();
+
+        GuardLoop {
+            obstacle: trial.obstacle,
+            entry: cycle[0].position,
+            length,
+            cycle,
+        }
+    }
+
+    ///The lab in puzzle notation with the obstacle as `O` and the loop drawn with `|`, `-` and `+`,
+    ///plus `/` and `\\` for diagonal patrols
+    fn render_loop(&self, guard_loop: &GuardLoop) -> String {
+        let mut grid: Vec<Vec<char>> = self
+            .lab
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match cell {
+                        &CellType::Guard(d) => GUARD_SYMBOLS[d / 2],
+                        CellType::Obstacle => '#',
+                        CellType::Empty => '.',
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for pose in &guard_loop.cycle {
+            let (x, y) = pose.position;
+            let along = match DIRECTIONS[pose.direction] {
+                (0, _) => '-',
+                (_, 0) => '|',
+                (dx, dy) if dx == dy => '\\',
+                _ => '/',
+            };
+            grid[x][y] = match grid[x][y] {
+                guard if GUARD_SYMBOLS.contains(&guard) => guard,
+                '.' => along,
+                current if current == along => along,
+                _ => '+',
+            };
+        }
+        let (ox, oy) = guard_loop.obstacle;
+        grid[ox][oy] = 'O';
+
+        grid.iter()
+            .map(|row| row.iter().chain(std::iter::once(&'\n')).collect::<String>())
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct GuardLoop {
+    obstacle: (usize, usize),
+    //Where the guard first steps onto the cycle
+    entry: (usize, usize),
+    //Number of cells moved to go round the cycle once
+    length: usize,
+    //Pose at every step of the cycle, turns included
+    cycle: Vec<Pose>,
+}
+
+impl GuardLoop {
+    fn cells(&self) -> HashSet<(usize, usize)> {
+        self.cycle.iter().map(|pose| pose.position).collect()
+    }
+}
+
+struct Coverage {
+    walks: Vec<(Guard, Walk)>,
+    //Cells visited by any guard
+    combined: HashSet<(usize, usize)>,
+}
+
+impl Coverage {
+    fn shared(&self) -> usize {
+        self.combined
+            .iter()
+            .filter(|cell| {
+                self.walks
+                    .iter()
+                    .filter(|(_, walk)| walk.visited.contains(cell))
+                    .count()
+                    > 1
+            })
+            .count()
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (guard, walk)) in self.walks.iter().enumerate() {
+            writeln!(
+                f,
+                "Guard {} at {:?} facing {}: {} cells, {}",
+                i + 1,
+                guard.position,
+                GUARD_SYMBOLS[guard.direction / 2],
+                walk.visited.len(),
+                if walk.loops {
+                    "walks in a loop"
+                } else {
+                    "leaves the lab"
+                }
+            )?;
+        }
+        write!(
+            f,
+            "Combined: {} cells, {} visited by more than one guard",
+            self.combined.len(),
+            self.shared()
+        )
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...
+";
+
+#[test]
+fn check_example() {
+    let lab = Lab::get_matrix(EXAMPLE.as_bytes()).unwrap();
+    let guard = lab.guards().unwrap()[0];
+
+    assert_eq!(lab.coverage().unwrap().combined.len(), 41);
+    assert_eq!(lab.count_multiverses_with_loops(&guard, 1), 6);
+    assert_eq!(lab.count_multiverses_with_loops(&guard, 4), 6);
+}
+
+#[test]
+fn check_multiple_guards() {
+    let lab = Lab::get_matrix(
+        "..#v.
+>...#
+..<..
+#....
+.#...
+"
+        .as_bytes(),
+    )
+    .unwrap();
+    let guards = lab.guards().unwrap();
+    assert_eq!(
+        guards,
+        vec![
+            Guard {
+                position: (0, 3),
+                direction: 4
+            },
+            Guard {
+                position: (1, 0),
+                direction: 2
+            },
+            Guard {
+                position: (2, 2),
+                direction: 6
+            },
+        ]
+    );
+
+    let coverage = lab.coverage().unwrap();
+    //Straight down and out at the bottom
+    assert_eq!(coverage.walks[0].1.visited.len(), 5);
+    //Turns down at (1, 3) and then follows the first guard's column
+    assert_eq!(coverage.walks[1].1.visited.len(), 7);
+    assert!(!coverage.walks[1].1.loops);
+    //Straight out to the left
+    assert_eq!(coverage.walks[2].1.visited.len(), 3);
+    assert_eq!(coverage.combined.len(), 11);
+    assert_eq!(coverage.shared(), 4);
+    assert_eq!(
+        coverage.to_string(),
+        "Guard 1 at (0, 3) facing v: 5 cells, leaves the lab
+Guard 2 at (1, 0) facing >: 7 cells, leaves the lab
+Guard 3 at (2, 2) facing <: 3 cells, leaves the lab
+Combined: 11 cells, 4 visited by more than one guard"
+    );
+}
+
+#[test]
+fn check_guard_already_in_a_loop() {
+    let lab = Lab::get_matrix(".#..\n.^.#\n#...\n..#.\n".as_bytes()).unwrap();
+    let guard = lab.guards().unwrap()[0];
+
+    assert!(lab.walk(&guard).loops);
+    assert_eq!(lab.count_multiverses_with_loops(&guard, 2), 0);
+}
+
+#[test]
+fn check_trapping_obstacles() {
+    let lab = Lab::get_matrix(EXAMPLE.as_bytes()).unwrap();
+    let loops = lab.trapping_obstacles(&lab.guards().unwrap()[0], 2);
+
+    assert_eq!(
+        loops
+            .iter()
+            .map(|guard_loop| guard_loop.obstacle)
+            .collect::<Vec<_>>(),
+        vec![(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]
+    );
+    assert_eq!(loops[0].entry, (6, 4));
+    assert_eq!(loops[0].length, 18);
+    assert_eq!(loops[0].cells().len(), 18);
+    assert_eq!(
+        lab.render_loop(&loops[0]),
+        "....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+"
+    );
+}
+
+#[test]
+fn check_turn_policies() {
+    assert_eq!(TurnPolicy::Right.turn(6, 0), 0);
+    assert_eq!(TurnPolicy::Left.turn(0, 0), 6);
+    assert_eq!(TurnPolicy::Reverse.turn(3, 0), 7);
+    assert_eq!(TurnPolicy::Alternate.turn(0, 0), 2);
+    assert_eq!(TurnPolicy::Alternate.turn(2, 1), 0);
+
+    let mut corridor = Lab::get_matrix("#\n.\n^\n.\n#\n".as_bytes()).unwrap();
+    let guard = corridor.guards().unwrap()[0];
+    //Turning right from (1, 0) walks straight out of a one column lab
+    assert!(!corridor.walk(&guard).loops);
+    corridor.patrol.turn = TurnPolicy::Reverse;
+    let walk = corridor.walk(&guard);
+    assert!(walk.loops);
+    assert_eq!(walk.visited.len(), 3);
+
+    //Up and to the right from (6, 4) until the obstacle at (1, 9), then down and to the right
+    let mut lab = Lab::get_matrix(EXAMPLE.as_bytes()).unwrap();
+    lab.patrol.diagonal = true;
+    let guard = lab.guards().unwrap()[0];
+    assert_eq!(lab.walk(&guard).visited.len(), 6);
+}
+
+///Random labs whose original walk leaves the lab under `patrol`, generated with a fixed-seed LCG
+#[cfg(test)]
+fn generated_labs(count: usize, patrol: Patrol) -> Vec<Lab> {
+    let mut seed: u64 = 0x2024_0006;
+    let mut next = move |bound: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % bound
+    };
+
+    let mut labs = Vec::new();
+    while labs.len() < count {
+        let (rows, columns) = (3 + next(14), 3 + next(14));
+        let mut text = String::new();
+        let guard = (next(rows), next(columns));
+        let symbol = GUARD_SYMBOLS[next(GUARD_SYMBOLS.len())];
+        for x in 0..rows {
+            for y in 0..columns {
+                text.push(match ((x, y) == guard, next(100) < 18) {
+                    (true, _) => symbol,
+                    (false, true) => '#',
+                    (false, false) => '.',
+                });
+            }
+            text.push('\n');
+        }
+
+        let mut lab = Lab::get_matrix(text.as_bytes()).unwrap();
+        lab.patrol = patrol;
+        if !lab.walk(&lab.guards().unwrap()[0]).loops {
+            labs.push(lab);
+        }
+    }
+
+    labs
+}
+
+#[test]
+fn check_jump_tables_against_brute_force() {
+    let policies = [
+        TurnPolicy::Right,
+        TurnPolicy::Left,
+        TurnPolicy::Reverse,
+        TurnPolicy::Alternate,
+    ];
+    for turn in policies {
+        for diagonal in [false, true] {
+            for mut lab in generated_labs(150, Patrol { turn, diagonal }) {
+                let guard = lab.guards().unwrap()[0];
+                let expected = lab
+                    .count_multiverses_with_loops_brute_force(&guard)
+                    .unwrap();
+                assert_eq!(lab.count_multiverses_with_loops(&guard, 3), expected);
+            }
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let file = File::open("inputs/input06.txt")?;
 
-    let mut lab = Lab::get_matrix(file)?;
+    let mut lab = Lab::get_matrix(io::BufReader::new(file))?;
+    if let Some(turn) = std::env::args().find_map(|arg| arg.strip_prefix("--turn=").map(str::parse))
+    {
+        lab.patrol.turn = turn?;
+    }
+    lab.patrol.diagonal = std::env::args().any(|arg| arg == "--diagonal");
+    let coverage = lab.coverage()?;
     //Part-1
-    println!("{}", lab.count_guard_walk()?.1);
+    println!("{}", coverage.combined.len());
     //5101
 
     //Part-2
-    println!("{}", lab.count_multiverses_with_loops()?);
+    let threads = thread::available_parallelism().map_or(1, NonZero::get);
+    let guards = lab.guards()?;
+    for guard in &guards {
+        println!("{}", lab.count_multiverses_with_loops(guard, threads));
+    }
     //1951
 
+    if std::env::args().any(|arg| arg == "--coverage") {
+        println!("{}", coverage);
+    }
+
+    if std::env::args().any(|arg| arg == "--loops") {
+        let render = std::env::args().any(|arg| arg == "--render");
+        for guard in &guards {
+            for guard_loop in lab.trapping_obstacles(guard, threads) {
+                println!(
+                    "Obstacle at {:?}: loop of {} steps over {} cells entered at {:?}",
+                    guard_loop.obstacle,
+                    guard_loop.length,
+                    guard_loop.cells().len(),
+                    guard_loop.entry
+                );
+                if render {
+                    println!("{}", lab.render_loop(&guard_loop));
+                }
+            }
+        }
+    }
+
     Ok(())
 }
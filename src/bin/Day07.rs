@@ -1,114 +1,645 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{Context, Result, anyhow};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
+use std::num::NonZero;
+use std::thread;
 
-fn get_calibration_equations(file: File) -> Result<HashMap<u64, Vec<u64>>> {
-    let reader = io::BufReader::new(file);
+///One line of the calibration file. Targets can repeat, so every line is kept.
+struct Equation {
+    line: usize,
+    target: u64,
+    values: Vec<u64>,
+}
+
+fn parse_equation(line: usize, text: &str) -> Result<Equation> {
+    let mut parts = text.split(':');
+    let target: u64 = parts
+        .next()
+        .ok_or_else(|| anyhow!("Missing key"))?
+        .trim()
+        .parse()?;
+    let values: Vec<u64> = parts
+        .next()
+        .ok_or_else(|| anyhow!("Missing values"))?
+        .split_whitespace()
+        .map(|c| Ok(c.trim().parse()?))
+        .collect::<Result<Vec<u64>>>()?;
 
+    Ok(Equation {
+        line,
+        target,
+        values,
+    })
+}
+
+fn get_calibration_equations(reader: impl BufRead) -> Result<Vec<Equation>> {
     reader
         .lines()
-        .map(|line| {
-            let line = line?;
-            let mut parts = line.split(':');
-            let key: u64 = parts
-                .next()
-                .ok_or_else(|| anyhow::anyhow!("Missing key"))?
-                .trim()
-                .parse()?;
-            let values: Vec<u64> = parts
-                .next()
-                .ok_or_else(|| anyhow::anyhow!("Missing values"))?
-                .split_whitespace()
-                .map(|c| Ok(c.trim().parse()?))
-                .collect::<Result<Vec<u64>>>()?;
-
-            Ok((key, values))
+        .enumerate()
+        .map(|(i, line)| {
+            parse_equation(i + 1, &line?)
+                .with_context(|| format!("Invalid equation on line {}", i + 1))
         })
-        .collect::<Result<HashMap<u64, Vec<u64>>>>()
+        .collect()
+}
+
+///A binary operator, evaluated left to right like the rest of the equation
+trait Operator: Sync {
+    fn symbol(&self) -> &'static str;
+
+    ///`None` if the result doesn't fit in a `u64` or is undefined
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64>;
+
+    ///Undoes the operator from the right: every `lhs` for which `apply(lhs, rhs) == Some(result)`
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse;
+}
+
+enum Inverse {
+    None,
+    One(u64),
+    //Too many candidates to list, so the left-hand side has to be evaluated forwards
+    Unknown,
 }
 
-fn can_obtain(target: u64, values: &[u64]) -> bool {
-    match (target, values) {
-        (0, _) => true,
-        (_, []) => false,
-        (t, [rest @ .., last]) => {
-            t == *last
-                || (t >= *last && can_obtain(t - last, rest))
-                || (t % last == 0 && can_obtain(target / last, rest))
+struct Add;
+struct Multiply;
+struct Concatenate;
+struct Subtract;
+struct Xor;
+struct Remainder;
+
+impl Operator for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_add(rhs)
+    }
+
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse {
+        result.checked_sub(rhs).map_or(Inverse::None, Inverse::One)
+    }
+}
+
+impl Operator for Multiply {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_mul(rhs)
+    }
+
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse {
+        match (result, rhs) {
+            //Anything times zero is zero
+            (0, 0) => Inverse::Unknown,
+            (_, 0) => Inverse::None,
+            _ if result.is_multiple_of(rhs) => Inverse::One(result / rhs),
+            _ => Inverse::None,
         }
     }
 }
 
-#[test]
-fn check_can_obtain() {
-    assert_eq!(can_obtain(190, &[19, 10]), true);
-    assert_eq!(can_obtain(3267, &[81, 40, 27]), true);
-    assert_eq!(can_obtain(83, &[17, 5]), false);
+///`10^digits`, the factor that shifts a number left to make room for `rhs`. `None` for 20-digit
+///numbers, where it doesn't fit in a `u64`.
+fn shift(rhs: u64) -> Option<u64> {
+    10u64.checked_pow(rhs.checked_ilog10().unwrap_or(0) + 1)
+}
+
+impl Operator for Concatenate {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        match shift(rhs) {
+            Some(shift) => lhs.checked_mul(shift)?.checked_add(rhs),
+            //A 20-digit `rhs` only fits with nothing in front of it
+            None if lhs == 0 => Some(rhs),
+            None => None,
+        }
+    }
+
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse {
+        match (result.checked_sub(rhs), shift(rhs)) {
+            (Some(0), None) => Inverse::One(0),
+            (Some(prefix), Some(shift)) if prefix.is_multiple_of(shift) => {
+                Inverse::One(prefix / shift)
+            }
+            _ => Inverse::None,
+        }
+    }
+}
+
+impl Operator for Subtract {
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_sub(rhs)
+    }
+
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse {
+        result.checked_add(rhs).map_or(Inverse::None, Inverse::One)
+    }
+}
+
+impl Operator for Xor {
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        Some(lhs ^ rhs)
+    }
+
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse {
+        Inverse::One(result ^ rhs)
+    }
+}
+
+impl Operator for Remainder {
+    fn symbol(&self) -> &'static str {
+        "%"
+    }
+
+    fn apply(&self, lhs: u64, rhs: u64) -> Option<u64> {
+        lhs.checked_rem(rhs)
+    }
+
+    fn unapply(&self, result: u64, rhs: u64) -> Inverse {
+        //Every `result + k * rhs` leaves `result`
+        if result < rhs {
+            Inverse::Unknown
+        } else {
+            Inverse::None
+        }
+    }
+}
+
+const PART_1_OPERATORS: [&dyn Operator; 2] = [&Add, &Multiply];
+const PART_2_OPERATORS: [&dyn Operator; 3] = [&Add, &Multiply, &Concatenate];
+
+fn get_operator(symbol: &str) -> Result<&'static dyn Operator> {
+    match symbol {
+        "+" => Ok(&Add),
+        "*" => Ok(&Multiply),
+        "||" => Ok(&Concatenate),
+        "-" => Ok(&Subtract),
+        "^" => Ok(&Xor),
+        "%" => Ok(&Remainder),
+        _ => Err(anyhow!("Invalid operator: {}", symbol)),
+    }
+}
+
+///Operators placed between the values of an equation, evaluated left to right
+struct Witness<'a> {
+    target: u64,
+    values: &'a [u64],
+    operators: Vec<&'a dyn Operator>,
+}
+
+impl Witness<'_> {
+    fn evaluate(&self) -> Option<u64> {
+        let (first, rest) = self.values.split_first()?;
+        rest.iter()
+            .zip(&self.operators)
+            .try_fold(*first, |acc, (&value, operator)| operator.apply(acc, value))
+    }
+}
+
+impl Display for Witness<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} =", self.target)?;
+        for (i, value) in self.values.iter().enumerate() {
+            if i > 0 {
+                write!(f, " {}", self.operators[i - 1].symbol())?;
+            }
+            write!(f, " {}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+///Evaluates every way of combining `values` left to right, starting from `acc`, and calls `visit`
+///with the result and the operators used. Stops early once `visit` returns `false`.
+fn for_each_evaluation<'a>(
+    acc: u64,
+    values: &[u64],
+    operators: &[&'a dyn Operator],
+    chosen: &mut Vec<&'a dyn Operator>,
+    visit: &mut impl FnMut(u64, &[&'a dyn Operator]) -> bool,
+) -> bool {
+    let [value, rest @ ..] = values else {
+        return visit(acc, chosen);
+    };
+
+    for &operator in operators {
+        if let Some(next) = operator.apply(acc, *value) {
+            chosen.push(operator);
+            let more = for_each_evaluation(next, rest, operators, chosen, visit);
+            chosen.pop();
+            if !more {
+                return false;
+            }
+        }
+    }
+
+    true
 }
 
-fn part_1(calibration_equations: &HashMap<u64, Vec<u64>>) -> u64 {
-    calibration_equations
+///Works back from the last value, un-applying each operator to prune the search, and collects
+///the operators right to left in `suffix`. Stops once `limit` witnesses are found.
+fn collect_witnesses<'a>(
+    target: u64,
+    values: &[u64],
+    operators: &[&'a dyn Operator],
+    suffix: &mut Vec<&'a dyn Operator>,
+    found: &mut Vec<Vec<&'a dyn Operator>>,
+    limit: usize,
+) {
+    let [rest @ .., last] = values else {
+        return;
+    };
+    let [first, middle @ ..] = rest else {
+        if target == *last {
+            found.push(suffix.iter().rev().copied().collect());
+        }
+        return;
+    };
+
+    for &operator in operators {
+        if found.len() >= limit {
+            return;
+        }
+
+        suffix.push(operator);
+        match operator.unapply(target, *last) {
+            Inverse::None => {}
+            Inverse::One(lhs) => collect_witnesses(lhs, rest, operators, suffix, found, limit),
+            Inverse::Unknown => {
+                for_each_evaluation(
+                    *first,
+                    middle,
+                    operators,
+                    &mut Vec::new(),
+                    &mut |lhs, prefix| {
+                        if operator.apply(lhs, *last) == Some(target) {
+                            found.push(prefix.iter().chain(suffix.iter().rev()).copied().collect());
+                        }
+                        found.len() < limit
+                    },
+                );
+            }
+        }
+        suffix.pop();
+    }
+}
+
+///Up to `limit` ways of placing `operators` between the values so that they evaluate to `target`
+fn witnesses<'a>(
+    target: u64,
+    values: &'a [u64],
+    operators: &[&'a dyn Operator],
+    limit: usize,
+) -> Vec<Witness<'a>> {
+    let mut found = Vec::new();
+    collect_witnesses(
+        target,
+        values,
+        operators,
+        &mut Vec::new(),
+        &mut found,
+        limit,
+    );
+
+    found
+        .into_iter()
+        .map(|operators| Witness {
+            target,
+            values,
+            operators,
+        })
+        .collect()
+}
+
+fn count_witnesses(target: u64, values: &[u64], operators: &[&dyn Operator]) -> u64 {
+    let [rest @ .., last] = values else {
+        return 0;
+    };
+    let [first, middle @ ..] = rest else {
+        return (target == *last) as u64;
+    };
+
+    operators
         .iter()
-        .filter_map(|(&key, value)| {
-            if can_obtain(key, value) {
-                Some(key)
-            } else {
-                None
+        .map(|operator| match operator.unapply(target, *last) {
+            Inverse::None => 0,
+            Inverse::One(lhs) => count_witnesses(lhs, rest, operators),
+            Inverse::Unknown => {
+                let mut count = 0;
+                for_each_evaluation(*first, middle, operators, &mut Vec::new(), &mut |lhs, _| {
+                    if operator.apply(lhs, *last) == Some(target) {
+                        count += 1;
+                    }
+                    true
+                });
+                count
             }
         })
         .sum()
 }
 
-fn can_obtain_with_concat(target: u64, values: &[u64]) -> bool {
-    match (target, values) {
-        (0, _) => true,
-        (_, []) => false,
-        (t, [rest @ .., last]) => {
-            t == *last
-                || (t >= *last && can_obtain_with_concat(t - last, rest))
-                || (t % last == 0 && can_obtain_with_concat(target / last, rest))
-                || {
-                    let target_str = t.to_string();
-                    let last_str = last.to_string();
-                    if target_str.len() > last_str.len() && target_str.ends_with(&last_str) {
-                        let new_target: u64 = target_str[..target_str.len() - last_str.len()]
-                            .parse()
-                            .unwrap();
-                        can_obtain_with_concat(new_target, rest)
-                    } else {
-                        false
-                    }
-                }
+fn can_obtain(target: u64, values: &[u64], operators: &[&dyn Operator]) -> bool {
+    !witnesses(target, values, operators, 1).is_empty()
+}
+
+#[test]
+fn check_can_obtain() {
+    assert!(can_obtain(190, &[19, 10], &PART_1_OPERATORS));
+    assert!(can_obtain(3267, &[81, 40, 27], &PART_1_OPERATORS));
+    assert!(!can_obtain(83, &[17, 5], &PART_1_OPERATORS));
+
+    assert!(!can_obtain(7290, &[6, 8, 6, 15], &PART_1_OPERATORS));
+    assert!(can_obtain(7290, &[6, 8, 6, 15], &PART_2_OPERATORS));
+    assert!(can_obtain(156, &[15, 6], &PART_2_OPERATORS));
+    assert!(!can_obtain(21037, &[9, 7, 18, 13], &PART_2_OPERATORS));
+}
+
+///Whether each line can be solved with each of the operator sets
+struct Calibration<'a> {
+    equation: &'a Equation,
+    solvable: Vec<bool>,
+}
+
+impl Display for Calibration<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.equation.line, self.equation.target)?;
+        for (operators, &solvable) in OPERATOR_SETS.iter().zip(&self.solvable) {
+            let symbols: Vec<&str> = operators.iter().map(|operator| operator.symbol()).collect();
+            write!(
+                f,
+                " [{}: {}]",
+                symbols.join(" "),
+                if solvable { "yes" } else { "no" }
+            )?;
         }
+
+        Ok(())
     }
 }
 
-fn part_2(calibration_equations: &HashMap<u64, Vec<u64>>) -> u64 {
-    calibration_equations
+const OPERATOR_SETS: [&[&dyn Operator]; 2] = [&PART_1_OPERATORS, &PART_2_OPERATORS];
+
+///Checks every equation against every set in `OPERATOR_SETS`, spreading the lines over `threads`
+fn calibrate(equations: &[Equation], threads: usize) -> Vec<Calibration<'_>> {
+    let chunk_size = equations.len().div_ceil(threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = equations
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|equation| Calibration {
+                            equation,
+                            solvable: OPERATOR_SETS
+                                .iter()
+                                .map(|operators| {
+                                    can_obtain(equation.target, &equation.values, operators)
+                                })
+                                .collect(),
+                        })
+                        .collect::<Vec<Calibration>>()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+///Sum of the targets that `OPERATOR_SETS[set]` can solve
+fn total(calibrations: &[Calibration], set: usize) -> Result<u64> {
+    calibrations
         .iter()
-        .filter_map(|(&key, value)| {
-            if can_obtain_with_concat(key, value) {
-                Some(key)
-            } else {
-                None
-            }
+        .filter(|calibration| calibration.solvable[set])
+        .try_fold(0u64, |total, calibration| {
+            total
+                .checked_add(calibration.equation.target)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Calibration total overflows on line {}",
+                        calibration.equation.line
+                    )
+                })
         })
-        .sum()
+}
+
+fn part_1(calibrations: &[Calibration]) -> Result<u64> {
+    total(calibrations, 0)
+}
+
+fn part_2(calibrations: &[Calibration]) -> Result<u64> {
+    total(calibrations, 1)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20";
+
+#[test]
+fn check_calibration() {
+    let equations = get_calibration_equations(EXAMPLE.as_bytes()).unwrap();
+    let calibrations = calibrate(&equations, 4);
+    assert_eq!(part_1(&calibrations).unwrap(), 3749);
+    assert_eq!(part_2(&calibrations).unwrap(), 11387);
+    assert_eq!(
+        calibrations[4].to_string(),
+        "line 5: 7290 [+ *: no] [+ * ||: yes]"
+    );
+
+    //A repeated target is its own line and counts twice
+    let repeated = format!("{}\n190: 19 10\n190: 1 1", EXAMPLE);
+    let equations = get_calibration_equations(repeated.as_bytes()).unwrap();
+    let calibrations = calibrate(&equations, 1);
+    assert_eq!(calibrations.len(), 11);
+    assert_eq!(part_1(&calibrations).unwrap(), 3749 + 190);
+    assert_eq!(calibrations[10].equation.line, 11);
+    assert_eq!(calibrations[10].solvable, vec![false, false]);
+
+    //Operands that overflow only rule out the operators that overflow
+    let huge = "18446744073709551615: 18446744073709551614 1\n2: 18446744073709551615 2 2";
+    let equations = get_calibration_equations(huge.as_bytes()).unwrap();
+    let calibrations = calibrate(&equations, 2);
+    assert_eq!(calibrations[0].solvable, vec![true, true]);
+    assert_eq!(calibrations[1].solvable, vec![false, false]);
+    assert!(part_1(&calibrations).is_ok());
+    let doubled = format!("{}\n{}", huge, huge);
+    let equations = get_calibration_equations(doubled.as_bytes()).unwrap();
+    assert!(part_1(&calibrate(&equations, 2)).is_err());
+
+    let error = get_calibration_equations("190: 10 19\n3267 81 40 27".as_bytes())
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "Invalid equation on line 2");
+}
+
+#[test]
+fn check_witnesses() {
+    let found: Vec<String> = witnesses(3267, &[81, 40, 27], &PART_1_OPERATORS, usize::MAX)
+        .iter()
+        .map(Witness::to_string)
+        .collect();
+    assert_eq!(found, vec!["3267 = 81 * 40 + 27", "3267 = 81 + 40 * 27"]);
+    assert_eq!(count_witnesses(3267, &[81, 40, 27], &PART_1_OPERATORS), 2);
+    assert_eq!(
+        witnesses(3267, &[81, 40, 27], &PART_1_OPERATORS, 1).len(),
+        1
+    );
+
+    assert!(witnesses(7290, &[6, 8, 6, 15], &PART_1_OPERATORS, 1).is_empty());
+    assert_eq!(
+        witnesses(7290, &[6, 8, 6, 15], &PART_2_OPERATORS, 1)[0].to_string(),
+        "7290 = 6 * 8 || 6 * 15"
+    );
+    assert_eq!(count_witnesses(83, &[17, 5], &PART_2_OPERATORS), 0);
+
+    //Anything times zero is zero, so all nine ways of combining 1, 2 and 3 work
+    assert_eq!(count_witnesses(0, &[1, 2, 3, 0], &PART_2_OPERATORS), 9);
+    assert_eq!(
+        witnesses(0, &[1, 2, 3, 0], &PART_2_OPERATORS, usize::MAX).len(),
+        9
+    );
+}
+
+#[test]
+fn check_operators() {
+    assert_eq!(Concatenate.apply(12, 345), Some(12345));
+    assert_eq!(Concatenate.apply(12, 0), Some(120));
+    assert_eq!(Concatenate.apply(u64::MAX, 1), None);
+    assert_eq!(
+        Concatenate.apply(0, 10_000_000_000_000_000_000),
+        Some(10_000_000_000_000_000_000)
+    );
+    assert_eq!(Concatenate.apply(1, 10_000_000_000_000_000_000), None);
+
+    //Pruning and evaluation agree on a 20-digit right-hand side: 0 + rhs and 0 || rhs both work
+    let values = [0, 10_000_000_000_000_000_000];
+    let found = witnesses(values[1], &values, &PART_2_OPERATORS, usize::MAX);
+    assert_eq!(found.len(), 2);
+    assert_eq!(count_witnesses(values[1], &values, &PART_2_OPERATORS), 2);
+    for witness in found {
+        assert_eq!(witness.evaluate(), Some(values[1]), "{}", witness);
+    }
+    assert!(!can_obtain(values[1], &[1, values[1]], &[&Concatenate]));
+    assert!(matches!(Concatenate.unapply(12345, 345), Inverse::One(12)));
+    assert!(matches!(Concatenate.unapply(12345, 45), Inverse::One(123)));
+    assert!(matches!(Concatenate.unapply(12345, 44), Inverse::None));
+
+    let all: Vec<&dyn Operator> = ["+", "*", "||", "-", "^", "%"]
+        .into_iter()
+        .map(get_operator)
+        .collect::<Result<_>>()
+        .unwrap();
+    assert!(get_operator("/").is_err());
+
+    //Every witness really evaluates to its target, whichever operators are mixed in
+    for (target, values) in [
+        (3, &[10, 7][..]),
+        (6, &[5, 3, 11, 2]),
+        (1, &[9, 4, 2]),
+        (42, &[7, 2, 3, 1]),
+        (0, &[4, 4, 4]),
+    ] {
+        let found = witnesses(target, values, &all, usize::MAX);
+        assert!(!found.is_empty(), "{} should be reachable", target);
+        assert_eq!(found.len() as u64, count_witnesses(target, values, &all));
+        for witness in found {
+            assert_eq!(witness.evaluate(), Some(target), "{}", witness);
+        }
+    }
+
+    //`9 % 4 = 1` can only be found by evaluating forwards
+    assert_eq!(
+        witnesses(1, &[9, 4], &[&Remainder], 1)[0].to_string(),
+        "1 = 9 % 4"
+    );
 }
 
 fn main() -> Result<()> {
     let file = File::open("inputs/input07.txt")?;
-    let calibration_equations = get_calibration_equations(file)?;
+    let equations = get_calibration_equations(io::BufReader::new(file))?;
+    let threads = thread::available_parallelism().map_or(1, NonZero::get);
+    let calibrations = calibrate(&equations, threads);
 
     //Part-1
-    println!("{}", part_1(&calibration_equations));
+    println!("{}", part_1(&calibrations)?);
     //7579994664753
 
     //Part-2
-    println!("{}", part_2(&calibration_equations));
+    println!("{}", part_2(&calibrations)?);
     //438027111276610
 
+    //`--lines` shows which operator sets solve each line.
+    //`--explain` shows one witness per equation, `--all` every witness and `--count` how many.
+    //`--operators=+,*,-` picks the operators, from `+ * || - ^ %`
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--lines") {
+        for calibration in &calibrations {
+            println!("{}", calibration);
+        }
+    }
+    if args.iter().any(|arg| arg == "--explain") {
+        let operators = match args.iter().find_map(|arg| arg.strip_prefix("--operators=")) {
+            Some(symbols) => symbols
+                .split(',')
+                .map(get_operator)
+                .collect::<Result<Vec<_>>>()?,
+            None => PART_2_OPERATORS.to_vec(),
+        };
+        let limit = if args.iter().any(|arg| arg == "--all") {
+            usize::MAX
+        } else {
+            1
+        };
+
+        for Equation {
+            line,
+            target,
+            values,
+        } in &equations
+        {
+            if args.iter().any(|arg| arg == "--count") {
+                println!(
+                    "line {}: {} witnesses",
+                    line,
+                    count_witnesses(*target, values, &operators)
+                );
+            }
+            for witness in witnesses(*target, values, &operators, limit) {
+                debug_assert_eq!(witness.evaluate(), Some(*target));
+                println!("{}", witness);
+            }
+        }
+    }
+
     Ok(())
 }
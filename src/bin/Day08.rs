@@ -1,10 +1,9 @@
-use anyhow::Result;
-use std::collections::{HashMap, HashSet};
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead};
 
-fn get_map(file: File) -> Result<(HashMap<char, Vec<(usize, usize)>>, usize, usize)> {
-    let reader = io::BufReader::new(file);
+fn get_map(reader: impl BufRead) -> Result<(HashMap<char, Vec<Position>>, usize, usize)> {
     let mut map = HashMap::new();
     let mut rows = 0;
     let mut columns = 0;
@@ -23,75 +22,645 @@ fn get_map(file: File) -> Result<(HashMap<char, Vec<(usize, usize)>>, usize, usi
     Ok((map, rows, columns))
 }
 
-//For two antennas at (r1,c1) and (r2,c2), the anti-nodes are at (2*r1-r2, 2*c1-c2) and (2*r2-r1,2*c2-c1)
-fn part_1(map: &HashMap<char, Vec<(usize, usize)>>, rows: usize, columns: usize) -> usize {
-    let mut anti_node_positions = HashSet::new();
-    let is_within_bounds = |x: isize, y: isize| -> bool {
-        x >= 0 && x < rows as isize && y >= 0 && y < columns as isize
-    };
+type Position = (usize, usize);
+type Pair = (Position, Position);
+///Every antinode of one frequency, with the antenna pairs that produce it
+type Antinodes = BTreeMap<Position, Vec<Pair>>;
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
 
-    for (_, positions) in map {
-        for i in 0..positions.len() {
-            for j in 0..positions.len() {
-                if i == j {
-                    continue;
+enum Resonance {
+    ///For a ratio `n/d`, the points `a + n/d * (b - a)` and `b + n/d * (a - b)` that land on the
+    ///grid. `2/1` gives the points twice as far from one antenna as from the other.
+    Ratios(Vec<(isize, isize)>),
+    ///Every grid point in line with both antennas, extending at most `max_harmonics` steps beyond
+    ///either antenna
+    Line { max_harmonics: Option<usize> },
+}
+
+impl std::str::FromStr for Resonance {
+    type Err = anyhow::Error;
+
+    ///`line`, `line:3` or `ratios:2,1/3,2/3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arguments) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "line" if arguments.is_empty() => Ok(Resonance::Line {
+                max_harmonics: None,
+            }),
+            "line" => Ok(Resonance::Line {
+                max_harmonics: Some(arguments.parse()?),
+            }),
+            "ratios" => arguments
+                .split(',')
+                .map(|ratio| {
+                    let (numerator, denominator) = ratio.split_once('/').unwrap_or((ratio, "1"));
+                    let denominator: isize = denominator.parse()?;
+                    if denominator <= 0 {
+                        return Err(anyhow!("Invalid ratio: {}", ratio));
+                    }
+                    Ok((numerator.parse()?, denominator))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Resonance::Ratios),
+            _ => Err(anyhow!("Invalid resonance: {}", s)),
+        }
+    }
+}
+
+impl Resonance {
+    ///The antinodes of the antennas at `a` and `b` on a `rows` x `columns` grid
+    fn antinodes(&self, a: Position, b: Position, rows: usize, columns: usize) -> Vec<Position> {
+        let is_within_bounds = |x: isize, y: isize| -> bool {
+            x >= 0 && x < rows as isize && y >= 0 && y < columns as isize
+        };
+        let (r1, c1) = (a.0 as isize, a.1 as isize);
+        let (dr, dc) = (b.0 as isize - r1, b.1 as isize - c1);
+        let mut points = Vec::new();
+
+        match self {
+            Resonance::Ratios(ratios) => {
+                for &(numerator, denominator) in ratios {
+                    let (nr, nc) = (numerator * dr, numerator * dc);
+                    if nr % denominator != 0 || nc % denominator != 0 {
+                        continue;
+                    }
+                    let (sr, sc) = (nr / denominator, nc / denominator);
+                    points.push((r1 + sr, c1 + sc));
+                    points.push((r1 + dr - sr, c1 + dc - sc));
                 }
-                let (r1, c1) = positions[i];
-                let (r2, c2) = positions[j];
-                let anti_node = (2 * r1 as isize - r2 as isize, 2 * c1 as isize - c2 as isize);
+            }
+            Resonance::Line { max_harmonics } => {
+                //Stepping by the reduced difference reaches every lattice point on the line
+                let g = gcd(dr, dc).max(1);
+                let (sr, sc) = (dr / g, dc / g);
+                let limit = max_harmonics.map_or(isize::MAX, |m| m as isize);
 
-                if is_within_bounds(anti_node.0, anti_node.1) {
-                    anti_node_positions.insert((anti_node.0 as usize, anti_node.1 as usize));
+                //`a` is step 0 and `b` is step `g`, so walk back from `a` and forward from there
+                let (mut k, mut r, mut c) = (0, r1, c1);
+                while k <= limit && is_within_bounds(r, c) {
+                    points.push((r, c));
+                    (k, r, c) = (k + 1, r - sr, c - sc);
+                }
+                let (mut k, mut r, mut c) = (1, r1 + sr, c1 + sc);
+                while k <= g.saturating_add(limit) && is_within_bounds(r, c) {
+                    points.push((r, c));
+                    (k, r, c) = (k.saturating_add(1), r + sr, c + sc);
                 }
             }
         }
+
+        let mut points: Vec<Position> = points
+            .into_iter()
+            .filter(|&(r, c)| is_within_bounds(r, c))
+            .map(|(r, c)| (r as usize, c as usize))
+            .collect();
+        //Overlapping ratios can produce the same point twice
+        points.sort_unstable();
+        points.dedup();
+
+        points
     }
+}
 
-    anti_node_positions.len()
+///The antinodes of every frequency under `resonance`
+fn antinodes(
+    map: &HashMap<char, Vec<Position>>,
+    rows: usize,
+    columns: usize,
+    resonance: &Resonance,
+) -> BTreeMap<char, Antinodes> {
+    map.iter()
+        .map(|(&frequency, positions)| {
+            let mut found = Antinodes::new();
+            for (i, &a) in positions.iter().enumerate() {
+                for &b in &positions[i + 1..] {
+                    for antinode in resonance.antinodes(a, b, rows, columns) {
+                        found.entry(antinode).or_default().push((a, b));
+                    }
+                }
+            }
+            (frequency, found)
+        })
+        .collect()
 }
 
-fn part_2(map: &HashMap<char, Vec<(usize, usize)>>, rows: usize, columns: usize) -> usize {
-    let mut anti_node_positions = HashSet::new();
-    let is_within_bounds = |x: isize, y: isize| -> bool {
-        x >= 0 && x < rows as isize && y >= 0 && y < columns as isize
-    };
+///Antennas that can be added, moved and removed, keeping count of how many antenna pairs produce
+///each antinode so that the number of unique antinodes is always at hand
+struct AntennaMap {
+    rows: usize,
+    columns: usize,
+    resonance: Resonance,
+    antennas: HashMap<char, Vec<Position>>,
+    occupied: HashMap<Position, char>,
+    counts: HashMap<Position, usize>,
+}
+
+impl AntennaMap {
+    fn new(
+        map: &HashMap<char, Vec<Position>>,
+        rows: usize,
+        columns: usize,
+        resonance: Resonance,
+    ) -> Result<Self> {
+        let mut antenna_map = AntennaMap {
+            rows,
+            columns,
+            resonance,
+            antennas: HashMap::new(),
+            occupied: HashMap::new(),
+            counts: HashMap::new(),
+        };
+        for (&frequency, positions) in map {
+            for &position in positions {
+                antenna_map.add(frequency, position)?;
+            }
+        }
+
+        Ok(antenna_map)
+    }
+
+    ///The antinodes `position` would form with the other antennas of `frequency`, once per pair
+    fn pair_antinodes(&self, frequency: char, position: Position) -> Vec<Position> {
+        self.antennas
+            .get(&frequency)
+            .into_iter()
+            .flatten()
+            .filter(|&&other| other != position)
+            .flat_map(|&other| {
+                self.resonance
+                    .antinodes(position, other, self.rows, self.columns)
+            })
+            .collect()
+    }
+
+    fn add(&mut self, frequency: char, position: Position) -> Result<()> {
+        if position.0 >= self.rows || position.1 >= self.columns {
+            return Err(anyhow!("Position {:?} is off the map", position));
+        }
+        if let Some(other) = self.occupied.get(&position) {
+            return Err(anyhow!("Antenna {} already at {:?}", other, position));
+        }
+
+        for antinode in self.pair_antinodes(frequency, position) {
+            *self.counts.entry(antinode).or_insert(0) += 1;
+        }
+        self.antennas.entry(frequency).or_default().push(position);
+        self.occupied.insert(position, frequency);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, position: Position) -> Result<char> {
+        let frequency = self
+            .occupied
+            .remove(&position)
+            .ok_or_else(|| anyhow!("No antenna at {:?}", position))?;
+
+        for antinode in self.pair_antinodes(frequency, position) {
+            if let Some(count) = self.counts.get_mut(&antinode) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&antinode);
+                }
+            }
+        }
+        let positions = self.antennas.get_mut(&frequency).unwrap();
+        positions.retain(|&other| other != position);
+
+        Ok(frequency)
+    }
+
+    fn move_antenna(&mut self, from: Position, to: Position) -> Result<()> {
+        let frequency = self.remove(from)?;
+        self.add(frequency, to).inspect_err(|_| {
+            //Put the antenna back so a failed move leaves the map unchanged
+            self.add(frequency, from).unwrap();
+        })
+    }
+
+    fn unique_antinodes(&self) -> usize {
+        self.counts.len()
+    }
+
+    ///How many new unique antinodes an antenna of `frequency` at `position` would add
+    fn gain(&self, frequency: char, position: Position) -> usize {
+        self.pair_antinodes(frequency, position)
+            .into_iter()
+            .filter(|antinode| !self.counts.contains_key(antinode))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    ///The free cell and existing frequency where one more antenna adds the most unique antinodes,
+    ///with the number it adds. Ties go to the smallest frequency, then the first cell.
+    fn best_new_antenna(&self) -> Option<(char, Position, usize)> {
+        let mut frequencies: Vec<char> = self.antennas.keys().copied().collect();
+        frequencies.sort_unstable();
+
+        frequencies
+            .into_iter()
+            .flat_map(|frequency| {
+                (0..self.rows)
+                    .flat_map(move |r| (0..self.columns).map(move |c| (frequency, (r, c))))
+            })
+            .filter(|(_, position)| !self.occupied.contains_key(position))
+            .map(|(frequency, position)| (frequency, position, self.gain(frequency, position)))
+            .rev()
+            .max_by_key(|&(_, _, gain)| gain)
+    }
+}
+
+impl AntennaMap {
+    ///The map in puzzle notation with `#` on every antinode and antennas drawn on top. Only the
+    ///antennas of `frequencies` and their antinodes are shown, or all of them for `None`.
+    fn render(&self, frequencies: Option<&[char]>, colored: bool) -> String {
+        const GREEN: &str = "\x1b[32m";
+        const RED: &str = "\x1b[31m";
+        const YELLOW: &str = "\x1b[33m";
+        const RESET: &str = "\x1b[0m";
+
+        let shown = |frequency: &char| frequencies.is_none_or(|chosen| chosen.contains(frequency));
+        let antinodes: HashSet<Position> = self
+            .antennas
+            .iter()
+            .filter(|(frequency, _)| shown(frequency))
+            .flat_map(|(&frequency, positions)| {
+                positions
+                    .iter()
+                    .flat_map(move |&position| self.pair_antinodes(frequency, position))
+            })
+            .collect();
 
-    for (_, positions) in map {
-        for i in 0..positions.len() {
-            for j in 0..positions.len() {
-                if i == j {
-                    continue;
+        let mut rendered = String::new();
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                let antinode = antinodes.contains(&(r, c));
+                let (symbol, colour) = match self.occupied.get(&(r, c)) {
+                    Some(frequency) if shown(frequency) => {
+                        (*frequency, if antinode { YELLOW } else { GREEN })
+                    }
+                    _ if antinode => ('#', RED),
+                    _ => ('.', ""),
+                };
+                if colored && !colour.is_empty() {
+                    rendered.push_str(colour);
+                    rendered.push(symbol);
+                    rendered.push_str(RESET);
+                } else {
+                    rendered.push(symbol);
                 }
+            }
+            rendered.push('\n');
+        }
+
+        rendered
+    }
+}
+
+///A change to an `AntennaMap`: `add:a:3,4`, `remove:3,4` or `move:3,4:5,6`
+enum Edit {
+    Add(char, Position),
+    Remove(Position),
+    Move(Position, Position),
+}
+
+fn parse_position(s: &str) -> Result<Position> {
+    let (r, c) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow!("Invalid position: {}", s))?;
+    Ok((r.parse()?, c.parse()?))
+}
 
-                let (r1, c1) = positions[i];
-                let (r2, c2) = positions[j];
+impl std::str::FromStr for Edit {
+    type Err = anyhow::Error;
 
-                let (dr, dc) = (r1 as isize - r2 as isize, c1 as isize - c2 as isize);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        match parts[..] {
+            ["add", frequency, position] if frequency.chars().count() == 1 => Ok(Edit::Add(
+                frequency.chars().next().unwrap(),
+                parse_position(position)?,
+            )),
+            ["remove", position] => Ok(Edit::Remove(parse_position(position)?)),
+            ["move", from, to] => Ok(Edit::Move(parse_position(from)?, parse_position(to)?)),
+            _ => Err(anyhow!("Invalid edit: {}", s)),
+        }
+    }
+}
+
+impl AntennaMap {
+    fn apply(&mut self, edit: &Edit) -> Result<()> {
+        match *edit {
+            Edit::Add(frequency, position) => self.add(frequency, position),
+            Edit::Remove(position) => self.remove(position).map(|_| ()),
+            Edit::Move(from, to) => self.move_antenna(from, to),
+        }
+    }
+}
+
+fn part_1(map: &HashMap<char, Vec<Position>>, rows: usize, columns: usize) -> Result<usize> {
+    let antenna_map = AntennaMap::new(map, rows, columns, Resonance::Ratios(vec![(2, 1)]))?;
+    Ok(antenna_map.unique_antinodes())
+}
+
+fn part_2(map: &HashMap<char, Vec<Position>>, rows: usize, columns: usize) -> Result<usize> {
+    let resonance = Resonance::Line {
+        max_harmonics: None,
+    };
+    Ok(AntennaMap::new(map, rows, columns, resonance)?.unique_antinodes())
+}
 
-                let (mut r, mut c) = (r1 as isize, c1 as isize);
-                while is_within_bounds(r, c) {
-                    anti_node_positions.insert((r as usize, c as usize));
-                    r += dr;
-                    c += dc;
+#[cfg(test)]
+const EXAMPLE: &str = "............
+........0...
+.....0......
+.......0....
+....0.......
+......A.....
+............
+............
+........A...
+.........A..
+............
+............";
+
+#[test]
+fn check_antinodes() {
+    let (map, rows, columns) = get_map(EXAMPLE.as_bytes()).unwrap();
+    assert_eq!(part_1(&map, rows, columns).unwrap(), 14);
+    assert_eq!(part_2(&map, rows, columns).unwrap(), 34);
+
+    let found = antinodes(&map, rows, columns, &"ratios:2".parse().unwrap());
+    assert_eq!(found[&'A'][&(7, 7)], vec![((8, 8), (9, 9))]);
+    //(1, 3) is an antinode of both frequencies, from different pairs
+    assert_eq!(found[&'A'][&(1, 3)], vec![((5, 6), (9, 9))]);
+    assert_eq!(found[&'0'][&(1, 3)], vec![((2, 5), (3, 7))]);
+    assert_eq!(found[&'A'].len() + found[&'0'].len(), 15);
+
+    //Three antennas in a row: the middle one is an antinode of the outer pair once the step is
+    //reduced, and two harmonics reach the corner but not past it
+    let (map, rows, columns) = get_map("a....\n.....\n..a..\n.....\n....a".as_bytes()).unwrap();
+    let found = antinodes(&map, rows, columns, &"line:0".parse().unwrap());
+    assert_eq!(found[&'a'].len(), 5);
+    assert_eq!(
+        found[&'a'][&(1, 1)],
+        vec![((0, 0), (2, 2)), ((0, 0), (4, 4))]
+    );
+    let found = antinodes(&map, rows, columns, &"ratios:1/2".parse().unwrap());
+    assert_eq!(
+        found[&'a'].keys().copied().collect::<Vec<_>>(),
+        vec![(1, 1), (2, 2), (3, 3)]
+    );
+
+    assert!("ratios:1/0".parse::<Resonance>().is_err());
+    assert!("plane".parse::<Resonance>().is_err());
+}
+
+#[test]
+fn check_antenna_map() {
+    let (map, rows, columns) = get_map(EXAMPLE.as_bytes()).unwrap();
+    let mut antenna_map =
+        AntennaMap::new(&map, rows, columns, Resonance::Ratios(vec![(2, 1)])).unwrap();
+    assert_eq!(antenna_map.unique_antinodes(), 14);
+
+    //Every edit must agree with rebuilding the map from scratch
+    let rebuilt = |antenna_map: &AntennaMap| {
+        AntennaMap::new(
+            &antenna_map.antennas,
+            rows,
+            columns,
+            Resonance::Ratios(vec![(2, 1)]),
+        )
+        .unwrap()
+        .unique_antinodes()
+    };
+    for edit in [
+        "remove:5,6",
+        "move:8,8:3,3",
+        "add:A:0,0",
+        "add:b:1,1",
+        "move:1,1:2,2",
+    ] {
+        antenna_map.apply(&edit.parse().unwrap()).unwrap();
+        assert_eq!(
+            antenna_map.unique_antinodes(),
+            rebuilt(&antenna_map),
+            "{}",
+            edit
+        );
+    }
+
+    //Failed edits leave the map as it was
+    let before = antenna_map.unique_antinodes();
+    assert!(antenna_map.apply(&"add:A:2,5".parse().unwrap()).is_err());
+    assert!(antenna_map.apply(&"remove:6,6".parse().unwrap()).is_err());
+    assert!(antenna_map.apply(&"move:3,3:1,8".parse().unwrap()).is_err());
+    assert!(antenna_map.apply(&"add:A:12,0".parse().unwrap()).is_err());
+    assert_eq!(antenna_map.unique_antinodes(), before);
+    assert_eq!(antenna_map.occupied[&(3, 3)], 'A');
+    assert!("add:AB:1,1".parse::<Edit>().is_err());
+
+    //The best new antenna really adds what it promises, and nothing adds more
+    let (frequency, position, gain) = antenna_map.best_new_antenna().unwrap();
+    let mut brute_force = 0;
+    for r in 0..rows {
+        for c in 0..columns {
+            for &frequency in antenna_map.antennas.keys() {
+                let mut trial = AntennaMap::new(
+                    &antenna_map.antennas,
+                    rows,
+                    columns,
+                    Resonance::Ratios(vec![(2, 1)]),
+                )
+                .unwrap();
+                if trial.add(frequency, (r, c)).is_ok() {
+                    brute_force = brute_force.max(trial.unique_antinodes() - before);
                 }
             }
         }
     }
+    assert_eq!(gain, brute_force);
+    antenna_map.add(frequency, position).unwrap();
+    assert_eq!(antenna_map.unique_antinodes(), before + gain);
+}
 
-    anti_node_positions.len()
+#[test]
+fn check_render() {
+    let (map, rows, columns) = get_map(EXAMPLE.as_bytes()).unwrap();
+    let resonant = AntennaMap::new(&map, rows, columns, Resonance::Ratios(vec![(2, 1)])).unwrap();
+    assert_eq!(
+        resonant.render(None, false),
+        "......#....#
+...#....0...
+....#0....#.
+..#....0....
+....0....#..
+.#....A.....
+...#........
+#......#....
+........A...
+.........A..
+..........#.
+..........#.
+"
+    );
+    assert_eq!(
+        resonant.render(Some(&['A']), false),
+        "............
+...#........
+....#.......
+............
+............
+......A.....
+............
+.......#....
+........A...
+.........A..
+..........#.
+..........#.
+"
+    );
+
+    let harmonic = AntennaMap::new(
+        &map,
+        rows,
+        columns,
+        Resonance::Line {
+            max_harmonics: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        harmonic.render(None, false),
+        "##....#....#
+.#.#....0...
+..#.#0....#.
+..##...0....
+....0....#..
+.#...#A....#
+...#..#.....
+#....#.#....
+..#.....A...
+....#....A..
+.#........#.
+...#......##
+"
+    );
+
+    let t_example = "T.........
+...T......
+.T........
+..........
+..........
+..........
+..........
+..........
+..........
+..........";
+    let (map, rows, columns) = get_map(t_example.as_bytes()).unwrap();
+    let harmonic = AntennaMap::new(
+        &map,
+        rows,
+        columns,
+        Resonance::Line {
+            max_harmonics: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(harmonic.unique_antinodes(), 9);
+    assert_eq!(
+        harmonic.render(None, false),
+        "T....#....
+...T......
+.T....#...
+.........#
+..#.......
+..........
+...#......
+..........
+....#.....
+..........
+"
+    );
+    //Every antenna here is also an antinode
+    assert!(
+        harmonic
+            .render(Some(&['T']), true)
+            .starts_with("\x1b[33mT\x1b[0m....\x1b[31m#\x1b[0m....\n")
+    );
 }
 
 fn main() -> Result<()> {
     let file = File::open("inputs/input08.txt")?;
-    let (map, rows, columns) = get_map(file)?;
+    let (map, rows, columns) = get_map(io::BufReader::new(file))?;
 
     //Part-1
-    println!("{}", part_1(&map, rows, columns));
+    println!("{}", part_1(&map, rows, columns)?);
     //409
 
     //Part-2
-    println!("{}", part_2(&map, rows, columns));
+    println!("{}", part_2(&map, rows, columns)?);
     //1308
 
+    //`--explain=<resonance>` lists which antenna pairs produce each antinode, e.g. `--explain=line`
+    //or `--explain=ratios:2,1/3,2/3`
+    if let Some(resonance) =
+        std::env::args().find_map(|arg| arg.strip_prefix("--explain=").map(String::from))
+    {
+        let resonance: Resonance = resonance.parse()?;
+        for (frequency, found) in antinodes(&map, rows, columns, &resonance) {
+            for (antinode, sources) in found {
+                let sources: Vec<String> = sources
+                    .iter()
+                    .map(|(a, b)| format!("{:?}-{:?}", a, b))
+                    .collect();
+                println!("{} {:?}: {}", frequency, antinode, sources.join(", "));
+            }
+        }
+    }
+
+    //`--edit=<edit>` (repeatable) changes the part 2 map and `--best` suggests one more antenna
+    let mut antenna_map = AntennaMap::new(
+        &map,
+        rows,
+        columns,
+        Resonance::Line {
+            max_harmonics: None,
+        },
+    )?;
+    for edit in std::env::args().filter_map(|arg| arg.strip_prefix("--edit=").map(String::from)) {
+        antenna_map.apply(&edit.parse()?)?;
+        println!("{}: {}", edit, antenna_map.unique_antinodes());
+    }
+    //`--render` draws the part 2 map, `--render=aA` only some frequencies, `--plain` without colour
+    if let Some(frequencies) = std::env::args().find_map(|arg| match arg.as_str() {
+        "--render" => Some(String::new()),
+        _ => arg.strip_prefix("--render=").map(String::from),
+    }) {
+        let frequencies: Vec<char> = frequencies.chars().collect();
+        let colored = !std::env::args().any(|arg| arg == "--plain");
+        print!(
+            "{}",
+            antenna_map.render(
+                (!frequencies.is_empty()).then_some(&frequencies[..]),
+                colored
+            )
+        );
+    }
+    if std::env::args().any(|arg| arg == "--best") {
+        match antenna_map.best_new_antenna() {
+            Some((frequency, position, gain)) => {
+                println!(
+                    "Add {} at {:?} for {} more antinodes",
+                    frequency, position, gain
+                )
+            }
+            None => println!("No antennas to pair with"),
+        }
+    }
+
     Ok(())
 }
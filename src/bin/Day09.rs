@@ -1,155 +1,916 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{self, BufRead};
 
-fn get_disk(file: File) -> Result<Vec<Option<u64>>> {
-    let reader = io::BufReader::new(file);
-    let mut disk = Vec::new();
-    let mut id = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    length: usize,
+}
+
+impl Span {
+    fn end(&self) -> usize {
+        self.start + self.length
+    }
+}
+
+#[derive(Clone)]
+struct Disk {
+    ///`files[id]` holds the spans of file `id`, sorted and merged
+    files: Vec<Vec<Span>>,
+    ///The gaps between files, sorted
+    free: Vec<Span>,
+    size: usize,
+}
+
+impl Disk {
+    ///Reads a disk map straight into runs, never expanding it into blocks. The map may be split
+    ///over several lines and contain whitespace anywhere.
+    fn parse(reader: impl BufRead) -> Result<Self> {
+        let mut files = Vec::new();
+        let mut position = 0;
+        let mut digits = 0;
 
-    if let Some(Ok(line)) = reader.lines().next() {
-        let chars: Vec<char> = line.chars().collect();
-        for (i, &char) in chars.iter().enumerate() {
-            let times = char
-                .to_digit(10)
-                .ok_or(anyhow::anyhow!("Should've been a digit"))?;
-            let value = if i % 2 == 0 { Some(id) } else { None };
-            disk.extend(std::iter::repeat(value).take(times as usize));
-            if i % 2 == 0 {
-                id += 1;
+        for (i, line) in reader.lines().enumerate() {
+            for c in line?.chars().filter(|c| !c.is_whitespace()) {
+                let length = c
+                    .to_digit(10)
+                    .ok_or_else(|| anyhow!("Invalid digit {:?} on line {}", c, i + 1))?
+                    as usize;
+                //The gaps are worked out by `normalise`
+                if digits % 2 == 0 {
+                    files.push(vec![Span {
+                        start: position,
+                        length,
+                    }]);
+                }
+                position += length;
+                digits += 1;
             }
         }
+
+        let mut disk = Disk {
+            files,
+            free: Vec::new(),
+            size: position,
+        };
+        disk.normalise();
+        Ok(disk)
     }
 
-    Ok(disk)
-}
+    ///Sorts and merges the spans of every file and rebuilds the free list from what's left
+    fn normalise(&mut self) {
+        let mut occupied = Vec::new();
+        for spans in &mut self.files {
+            spans.retain(|span| span.length > 0);
+            spans.sort_unstable_by_key(|span| span.start);
 
-fn part_1(disk: &[Option<u64>]) -> u64 {
-    let compacted_disk = {
-        //Swap the dots and file blocks
-        let mut compacted_disk = disk.to_vec();
-        let mut left = 0;
-        let mut right = disk.len().saturating_sub(1);
+            let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+            for &span in spans.iter() {
+                match merged.last_mut() {
+                    Some(last) if last.end() == span.start => last.length += span.length,
+                    _ => merged.push(span),
+                }
+            }
+            occupied.extend_from_slice(&merged);
+            *spans = merged;
+        }
+        occupied.sort_unstable_by_key(|span| span.start);
 
-        while left < right {
-            while left < right && disk[left].is_some() {
-                left += 1;
+        self.free.clear();
+        let mut position = 0;
+        for span in occupied.into_iter().chain([Span {
+            start: self.size,
+            length: 0,
+        }]) {
+            if span.start > position {
+                self.free.push(Span {
+                    start: position,
+                    length: span.start - position,
+                });
             }
-            while left < right && disk[right].is_none() {
-                right -= 1;
+            position = span.end();
+        }
+    }
+
+    fn blocks(&self) -> Vec<Option<u64>> {
+        let mut blocks = vec![None; self.size];
+        for (id, spans) in self.files.iter().enumerate() {
+            for span in spans {
+                blocks[span.start..span.end()].fill(Some(id as u64));
             }
-            if left < right {
-                compacted_disk.swap(left, right);
-                left += 1;
-                right = right.saturating_sub(1);
+        }
+
+        blocks
+    }
+
+    ///The disk in the puzzle's notation, like `0099811188827773336446555566..............`
+    fn layout(&self) -> String {
+        self.frames(&[]).remove(0)
+    }
+
+    fn checksum(&self) -> u128 {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, spans)| spans.iter().map(move |span| (id as u128, span)))
+            .map(|(id, span)| {
+                //id * (start + (start + 1) + ... + (end - 1))
+                let (start, length) = (span.start as u128, span.length as u128);
+                id * (length * start + length * length.saturating_sub(1) / 2)
+            })
+            .sum()
+    }
+
+    fn compacted(&self, strategy: &dyn CompactionStrategy) -> Disk {
+        let mut disk = self.clone();
+        strategy.compact(&mut disk, &mut |_| {});
+        disk
+    }
+
+    ///Every move `strategy` makes while compacting the disk, in order
+    fn trace(&self, strategy: &dyn CompactionStrategy) -> Vec<Move> {
+        let mut moves = Vec::new();
+        strategy.compact(&mut self.clone(), &mut |step| moves.push(step));
+        moves
+    }
+
+    ///The layout before and after each of `moves`, block by block for block-level moves, as the
+    ///puzzle draws them
+    fn frames(&self, moves: &[Move]) -> Vec<String> {
+        let mut blocks = self.blocks();
+        let draw = |blocks: &[Option<u64>]| -> String {
+            blocks
+                .iter()
+                .map(|block| block.map_or(".".to_string(), |id| id.to_string()))
+                .collect()
+        };
+
+        let mut frames = vec![draw(&blocks)];
+        for step in moves {
+            if step.blockwise {
+                //The last block goes first, into the leftmost free block
+                for k in 0..step.length {
+                    blocks[step.from + step.length - 1 - k] = None;
+                    blocks[step.to + k] = Some(step.id as u64);
+                    frames.push(draw(&blocks));
+                }
+            } else {
+                blocks[step.from..step.from + step.length].fill(None);
+                blocks[step.to..step.to + step.length].fill(Some(step.id as u64));
+                frames.push(draw(&blocks));
             }
         }
 
-        compacted_disk
-    };
+        frames
+    }
 
-    compacted_disk
-        .iter()
-        .enumerate()
-        .fold(0, |acc, (position, file_id)| {
-            acc + position as u64 * file_id.unwrap_or(0)
-        })
+    ///Puts the whole of file `id` in one piece at `to`, reporting a move for each of its spans
+    fn relocate(&mut self, id: usize, to: usize, observe: &mut dyn FnMut(Move)) {
+        let mut position = to;
+        for span in &self.files[id] {
+            if span.start != position {
+                observe(Move {
+                    id,
+                    from: span.start,
+                    to: position,
+                    length: span.length,
+                    blockwise: false,
+                });
+            }
+            position += span.length;
+        }
+
+        self.files[id] = vec![Span {
+            start: to,
+            length: position - to,
+        }];
+    }
+}
+
+///`length` blocks of file `id` moved from `from` to `to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    id: usize,
+    from: usize,
+    to: usize,
+    length: usize,
+    ///Whether the blocks moved one at a time, last block first
+    blockwise: bool,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.blockwise && self.length > 1 {
+            write!(f, "{} blocks of ", self.length)?;
+        }
+        write!(
+            f,
+            "file {} moved from {} to {}",
+            self.id, self.from, self.to
+        )
+    }
 }
 
-fn blanks(disk: &[Option<u64>]) -> Vec<(usize, usize)> {
-    let mut blanks = Vec::new();
-    let mut start = None;
+trait CompactionStrategy {
+    ///Compacts the disk, calling `observe` with every move as it's made
+    fn compact(&self, disk: &mut Disk, observe: &mut dyn FnMut(Move));
+}
+
+///Moves file blocks from the end of the disk into the leftmost free block, as many at once as
+///the last file run and the first gap allow
+struct BlockLevel;
+
+///Moves each whole file once, highest id first, into the leftmost free span that fits
+struct FirstFit;
+
+///Like `FirstFit`, but into the smallest free span that fits
+struct BestFit;
+
+///Closes every gap, so that each file is in one piece and all the free space is at the end
+struct Defragment;
 
-    disk.iter()
-        .enumerate()
-        .for_each(|(i, &val)| match (start, val) {
-            (None, None) => start = Some(i),
-            (Some(s), Some(_)) => {
-                blanks.push((s, i - s));
-                start = None;
+impl CompactionStrategy for BlockLevel {
+    fn compact(&self, disk: &mut Disk, observe: &mut dyn FnMut(Move)) {
+        //Every file span, rightmost last
+        let mut pieces: Vec<(usize, Span)> = disk
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(id, spans)| spans.iter().map(move |&span| (id, span)))
+            .collect();
+        pieces.sort_unstable_by_key(|(_, span)| span.start);
+
+        let mut moved = Vec::new();
+        let mut gaps = disk.free.iter().copied();
+        let mut gap = gaps.next();
+        while let (Some(hole), Some((id, piece))) = (&mut gap, pieces.last_mut()) {
+            if hole.start >= piece.start {
+                break;
             }
-            _ => {}
-        });
 
-    blanks
+            //Same as moving the blocks one by one, as the gap is filled left to right from the end
+            //of the piece
+            let length = hole.length.min(piece.length);
+            observe(Move {
+                id: *id,
+                from: piece.end() - length,
+                to: hole.start,
+                length,
+                blockwise: true,
+            });
+            moved.push((
+                *id,
+                Span {
+                    start: hole.start,
+                    length,
+                },
+            ));
+            (hole.start, hole.length) = (hole.start + length, hole.length - length);
+            piece.length -= length;
+
+            if piece.length == 0 {
+                pieces.pop();
+            }
+            if hole.length == 0 {
+                gap = gaps.next();
+            }
+        }
+
+        disk.files.iter_mut().for_each(Vec::clear);
+        for (id, span) in pieces.into_iter().chain(moved) {
+            disk.files[id].push(span);
+        }
+        disk.normalise();
+    }
 }
 
-fn collect_files(disk: &[Option<u64>]) -> HashMap<Option<u64>, (usize, usize)> {
-    let mut files = HashMap::new();
-    let mut current_id = None;
-    let mut start_index = 0;
+///Moves each whole file once, highest id first, into the free span left of it that `choose` picks
+///from the candidates, if any. The space a file leaves behind isn't reused.
+fn move_whole_files(
+    disk: &mut Disk,
+    choose: impl Fn(&[Span], usize) -> Option<usize>,
+    observe: &mut dyn FnMut(Move),
+) {
+    for id in (0..disk.files.len()).rev() {
+        let Some(first) = disk.files[id].first() else {
+            continue;
+        };
+        let size: usize = disk.files[id].iter().map(|span| span.length).sum();
+        let candidates = disk.free.partition_point(|gap| gap.start < first.start);
 
-    for (i, &val) in disk.iter().enumerate() {
-        match (current_id, val) {
-            (None, Some(id)) => {
-                current_id = Some(id);
-                start_index = i;
-            }
-            (Some(id), Some(new_id)) if id != new_id => {
-                files.insert(Some(id), (start_index, i - start_index));
-                current_id = Some(new_id);
-                start_index = i;
+        if let Some(i) = choose(&disk.free[..candidates], size) {
+            let start = disk.free[i].start;
+            disk.relocate(id, start, observe);
+
+            let gap = &mut disk.free[i];
+            (gap.start, gap.length) = (gap.start + size, gap.length - size);
+            if gap.length == 0 {
+                disk.free.remove(i);
             }
-            (Some(_), None) => {
-                files.insert(current_id, (start_index, i - start_index));
-                current_id = None;
+        }
+    }
+
+    disk.normalise();
+}
+
+///Gaps at least this long share the last `FirstFit` heap, as no parsed file is longer
+const LONG_GAP: usize = 9;
+
+impl CompactionStrategy for FirstFit {
+    ///Keeps the gaps in a min-heap per gap length up to `LONG_GAP`, so the leftmost gap that fits
+    ///is the leftmost of at most ten heap tops
+    fn compact(&self, disk: &mut Disk, observe: &mut dyn FnMut(Move)) {
+        let bucket = |length: usize| length.min(LONG_GAP);
+        let mut gaps: Vec<BinaryHeap<Reverse<(usize, usize)>>> =
+            vec![BinaryHeap::new(); LONG_GAP + 1];
+        for gap in &disk.free {
+            gaps[bucket(gap.length)].push(Reverse((gap.start, gap.length)));
+        }
+
+        for id in (0..disk.files.len()).rev() {
+            let Some(first) = disk.files[id].first() else {
+                continue;
+            };
+            let size: usize = disk.files[id].iter().map(|span| span.length).sum();
+            let leftmost = if size <= LONG_GAP {
+                gaps[size..]
+                    .iter()
+                    .filter_map(|heap| heap.peek().map(|&Reverse(gap)| gap))
+                    .min()
+            } else {
+                //Only a file put together by hand is this long, so look through every long gap
+                gaps[LONG_GAP]
+                    .iter()
+                    .map(|&Reverse(gap)| gap)
+                    .filter(|&(_, length)| length >= size)
+                    .min()
+            };
+
+            if let Some((start, length)) = leftmost.filter(|&(start, _)| start < first.start) {
+                let heap = &mut gaps[bucket(length)];
+                if heap.peek() == Some(&Reverse((start, length))) {
+                    heap.pop();
+                } else {
+                    heap.retain(|&Reverse(gap)| gap != (start, length));
+                }
+                if length > size {
+                    gaps[bucket(length - size)].push(Reverse((start + size, length - size)));
+                }
+                disk.relocate(id, start, observe);
             }
-            _ => {}
         }
+
+        disk.normalise();
     }
+}
 
-    if let Some(id) = current_id {
-        files.insert(Some(id), (start_index, disk.len() - start_index));
+///The plain scan `FirstFit` replaced, to check it against
+#[cfg(test)]
+fn first_fit_by_scanning(disk: &mut Disk) {
+    move_whole_files(
+        disk,
+        |gaps, size| gaps.iter().position(|gap| gap.length >= size),
+        &mut |_| {},
+    );
+}
+
+impl CompactionStrategy for BestFit {
+    fn compact(&self, disk: &mut Disk, observe: &mut dyn FnMut(Move)) {
+        move_whole_files(
+            disk,
+            |gaps, size| {
+                (0..gaps.len())
+                    .filter(|&i| gaps[i].length >= size)
+                    .min_by_key(|&i| gaps[i].length)
+            },
+            observe,
+        );
     }
+}
 
-    files
+///Which file holds which blocks, kept up to date move by move
+struct Occupancy {
+    ///The start of every file span, with its file and length
+    owners: BTreeMap<usize, (usize, usize)>,
+    ///The starts and lengths of the spans of each file
+    files: Vec<BTreeMap<usize, usize>>,
+    ///The starts and lengths of the free spans, merged
+    free: BTreeMap<usize, usize>,
 }
 
-fn calculate_total(files: &HashMap<Option<u64>, (usize, usize)>) -> u64 {
-    files
-        .iter()
-        .map(|(&fid, &(pos, size))| {
-            (pos..pos + size)
-                .map(|x| fid.unwrap() * x as u64)
-                .sum::<u64>()
-        })
-        .sum()
+impl Occupancy {
+    fn new(disk: &Disk) -> Self {
+        let mut occupancy = Occupancy {
+            owners: BTreeMap::new(),
+            files: vec![BTreeMap::new(); disk.files.len()],
+            free: disk
+                .free
+                .iter()
+                .map(|gap| (gap.start, gap.length))
+                .collect(),
+        };
+        for (id, spans) in disk.files.iter().enumerate() {
+            for span in spans {
+                occupancy.owners.insert(span.start, (id, span.length));
+                occupancy.files[id].insert(span.start, span.length);
+            }
+        }
+
+        occupancy
+    }
+
+    ///The leftmost free blocks at or after `position`
+    fn free_from(&self, position: usize) -> Option<Span> {
+        self.free
+            .range(..position)
+            .next_back()
+            .filter(|&(&start, &length)| start + length > position)
+            .map(|(&start, &length)| Span {
+                start: position,
+                length: start + length - position,
+            })
+            .or_else(|| {
+                self.free
+                    .range(position..)
+                    .next()
+                    .map(|(&start, &length)| Span { start, length })
+            })
+    }
+
+    ///Moves `length` blocks of file `id` from `from` to `to`, which must be free once they've
+    ///been vacated
+    fn move_blocks(
+        &mut self,
+        id: usize,
+        from: usize,
+        to: usize,
+        length: usize,
+        observe: &mut dyn FnMut(Move),
+    ) {
+        //Cut the blocks out of the span that holds them
+        let (&start, &(_, span_length)) = self.owners.range(..=from).next_back().unwrap();
+        self.owners.remove(&start);
+        self.files[id].remove(&start);
+        for (piece, piece_length) in [
+            (start, from - start),
+            (from + length, start + span_length - from - length),
+        ] {
+            if piece_length > 0 {
+                self.owners.insert(piece, (id, piece_length));
+                self.files[id].insert(piece, piece_length);
+            }
+        }
+
+        //Free them, merging with the free spans either side
+        let mut vacated = (from, length);
+        let before = self.free.range(..from).next_back();
+        if let Some((&gap, &gap_length)) = before.filter(|&(&gap, &l)| gap + l == from) {
+            self.free.remove(&gap);
+            vacated = (gap, gap_length + length);
+        }
+        if let Some(gap_length) = self.free.remove(&(from + length)) {
+            vacated.1 += gap_length;
+        }
+        self.free.insert(vacated.0, vacated.1);
+
+        //Take the destination out of the free span it lies in
+        let (&gap, &gap_length) = self.free.range(..=to).next_back().unwrap();
+        debug_assert!(
+            gap + gap_length >= to + length,
+            "{} blocks at {}",
+            length,
+            to
+        );
+        self.free.remove(&gap);
+        for (piece, piece_length) in [
+            (gap, to - gap),
+            (to + length, gap + gap_length - to - length),
+        ] {
+            if piece_length > 0 {
+                self.free.insert(piece, piece_length);
+            }
+        }
+
+        //Join the blocks to the file's spans either side
+        let mut landed = (to, length);
+        let before = self.files[id].range(..to).next_back();
+        if let Some((&span, &span_length)) = before.filter(|&(&span, &l)| span + l == to) {
+            self.files[id].remove(&span);
+            self.owners.remove(&span);
+            landed = (span, span_length + length);
+        }
+        if let Some(span_length) = self.files[id].remove(&(to + length)) {
+            self.owners.remove(&(to + length));
+            landed.1 += span_length;
+        }
+        self.owners.insert(landed.0, (id, landed.1));
+        self.files[id].insert(landed.0, landed.1);
+
+        observe(Move {
+            id,
+            from,
+            to,
+            length,
+            blockwise: false,
+        });
+    }
 }
 
-fn part_2(disk: &[Option<u64>]) -> u64 {
-    let mut files = collect_files(disk);
-    let mut blank_spaces = blanks(disk);
-    let mut file_id = files.keys().filter_map(|&k| k).max().unwrap();
+impl CompactionStrategy for Defragment {
+    ///Packs the files from the left in the order of their first blocks. Other files in the way
+    ///are moved into free space first, so that no move lands on occupied blocks.
+    fn compact(&self, disk: &mut Disk, observe: &mut dyn FnMut(Move)) {
+        let mut order: Vec<usize> = (0..disk.files.len())
+            .filter(|&id| !disk.files[id].is_empty())
+            .collect();
+        order.sort_unstable_by_key(|&id| disk.files[id][0].start);
+
+        let mut occupancy = Occupancy::new(disk);
+        let mut position = 0;
+        'packing: for id in order {
+            let end = position + occupancy.files[id].values().sum::<usize>();
 
-    while file_id > 0 {
-        if let Some(&(position, size)) = files.get(&Some(file_id)) {
-            for (i, &(start, len)) in blank_spaces.iter().enumerate() {
-                if start >= position {
-                    blank_spaces = blank_spaces[..i].to_owned();
+            loop {
+                let other = occupancy
+                    .owners
+                    .range(position..end)
+                    .find(|&(_, &(owner, _))| owner != id)
+                    .map(|(&start, &owner)| (start, owner));
+                let Some((start, (other, length))) = other else {
                     break;
+                };
+
+                if let Some(gap) = occupancy.free_from(end) {
+                    let length = length.min(end - start).min(gap.length);
+                    occupancy.move_blocks(other, start, gap.start, length, observe);
+                } else if let Some(gap) = occupancy.free_from(position) {
+                    //All the free space is where the file goes, so fill some of it with the end of
+                    //the file, which must lie beyond
+                    let (&last, &last_length) = occupancy.files[id].last_key_value().unwrap();
+                    let length = gap.length.min(last + last_length - end.max(last));
+                    occupancy.move_blocks(
+                        id,
+                        last + last_length - length,
+                        gap.start,
+                        length,
+                        observe,
+                    );
+                } else {
+                    //A full disk has nowhere to move anything to
+                    break 'packing;
                 }
+            }
 
-                if size <= len {
-                    files.insert(Some(file_id), (start, size));
-                    if size == len {
-                        blank_spaces.remove(i);
-                    } else {
-                        blank_spaces[i] = (start + size, len - size);
-                    }
-                    break;
+            //Only the file's own blocks and free ones are left where it goes, so its spans can
+            //move there in order
+            let spans: Vec<(usize, usize)> = occupancy.files[id]
+                .iter()
+                .map(|(&start, &length)| (start, length))
+                .collect();
+            let mut to = position;
+            for (start, length) in spans {
+                if start != to {
+                    occupancy.move_blocks(id, start, to, length, observe);
                 }
+                to += length;
+            }
+            position = end;
+        }
+
+        for (spans, file) in disk.files.iter_mut().zip(occupancy.files) {
+            *spans = file
+                .into_iter()
+                .map(|(start, length)| Span { start, length })
+                .collect();
+        }
+        disk.normalise();
+    }
+}
+
+fn get_strategy(name: &str) -> Result<&'static dyn CompactionStrategy> {
+    match name {
+        "blocks" => Ok(&BlockLevel),
+        "first-fit" => Ok(&FirstFit),
+        "best-fit" => Ok(&BestFit),
+        "defragment" => Ok(&Defragment),
+        _ => Err(anyhow!("Invalid compaction strategy: {}", name)),
+    }
+}
+
+fn part_1(disk: &Disk) -> u128 {
+    disk.compacted(&BlockLevel).checksum()
+}
+
+fn part_2(disk: &Disk) -> u128 {
+    disk.compacted(&FirstFit).checksum()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "2333133121414131402";
+
+#[test]
+fn check_compaction() {
+    let disk = Disk::parse(EXAMPLE.as_bytes()).unwrap();
+    assert_eq!(disk.layout(), "00...111...2...333.44.5555.6666.777.888899");
+    assert_eq!(part_1(&disk), 1928);
+    assert_eq!(part_2(&disk), 2858);
+
+    let layouts: Vec<String> = ["blocks", "first-fit", "best-fit", "defragment"]
+        .into_iter()
+        .map(|name| disk.compacted(get_strategy(name).unwrap()).layout())
+        .collect();
+    assert_eq!(
+        layouts,
+        vec![
+            "0099811188827773336446555566..............",
+            "00992111777.44.333....5555.6666.....8888..",
+            "00992111777.44.333....5555.6666.....8888..",
+            "0011123334455556666777888899.............."
+        ]
+    );
+
+    //First fit takes the leftmost gap that fits, best fit the smallest
+    let disk = Disk::parse("14221".as_bytes()).unwrap();
+    assert_eq!(disk.layout(), "0....11..2");
+    assert_eq!(disk.compacted(&FirstFit).layout(), "0211......");
+    assert_eq!(disk.compacted(&BestFit).layout(), "011....2..");
+
+    let disk = Disk::parse("12345".as_bytes()).unwrap();
+    assert_eq!(disk.compacted(&BlockLevel).layout(), "022111222......");
+    assert_eq!(disk.compacted(&BlockLevel).files[2].len(), 2);
+    //Defragmenting joins the pieces that block-level compaction leaves
+    let blocks = disk.compacted(&BlockLevel);
+    let defragmented = blocks.compacted(&Defragment);
+    assert_eq!(defragmented.layout(), "022222111......");
+    assert!(defragmented.files.iter().all(|spans| spans.len() == 1));
+    assert_eq!(
+        blocks.frames(&blocks.trace(&Defragment)).last().unwrap(),
+        "022222111......"
+    );
+    let example = Disk::parse(EXAMPLE.as_bytes())
+        .unwrap()
+        .compacted(&BlockLevel);
+    assert_eq!(
+        example.compacted(&Defragment).layout(),
+        "0099888811127773336666445555.............."
+    );
+    //`01.00`: the only free block is where file 0 goes, so file 0 fills it before file 1 moves
+    let hand_built = |files: Vec<Vec<(usize, usize)>>, size: usize| {
+        let mut disk = Disk {
+            files: files
+                .into_iter()
+                .map(|spans| {
+                    spans
+                        .into_iter()
+                        .map(|(start, length)| Span { start, length })
+                        .collect()
+                })
+                .collect(),
+            free: Vec::new(),
+            size,
+        };
+        disk.normalise();
+        disk
+    };
+    let disk = hand_built(vec![vec![(0, 1), (3, 2)], vec![(1, 1)]], 5);
+    assert_eq!(disk.compacted(&Defragment).layout(), "0001.");
+    assert_eq!(
+        disk.frames(&disk.trace(&Defragment)),
+        vec!["01.00", "0100.", "0.001", "000.1", "0001."]
+    );
+    //With no free block at all nothing can move
+    let disk = hand_built(vec![vec![(0, 1), (2, 1)], vec![(1, 1)]], 3);
+    assert!(disk.trace(&Defragment).is_empty());
+    assert_eq!(disk.compacted(&Defragment).layout(), "010");
+    assert!(get_strategy("worst-fit").is_err());
+}
+
+///Random disk maps of up to `digits` digits, generated with a fixed-seed LCG
+#[cfg(test)]
+fn generated_disk_maps(count: usize, digits: usize) -> Vec<String> {
+    let mut seed: u64 = 0x2024_0009;
+    let mut next = move |bound: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % bound
+    };
+
+    (0..count)
+        .map(|_| {
+            (0..1 + next(digits))
+                .map(|_| char::from_digit(next(10) as u32, 10).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn check_trace() {
+    let disk = Disk::parse("12345".as_bytes()).unwrap();
+    assert_eq!(
+        disk.frames(&disk.trace(&BlockLevel)),
+        vec![
+            "0..111....22222",
+            "02.111....2222.",
+            "022111....222..",
+            "0221112...22...",
+            "02211122..2....",
+            "022111222......"
+        ]
+    );
+
+    let disk = Disk::parse(EXAMPLE.as_bytes()).unwrap();
+    let frames = disk.frames(&disk.trace(&BlockLevel));
+    assert_eq!(frames.len(), 13);
+    assert_eq!(frames[1], "009..111...2...333.44.5555.6666.777.88889.");
+    assert_eq!(frames[12], "0099811188827773336446555566..............");
+
+    let moves = disk.trace(&FirstFit);
+    assert_eq!(
+        disk.frames(&moves),
+        vec![
+            "00...111...2...333.44.5555.6666.777.888899",
+            "0099.111...2...333.44.5555.6666.777.8888..",
+            "0099.1117772...333.44.5555.6666.....8888..",
+            "0099.111777244.333....5555.6666.....8888..",
+            "00992111777.44.333....5555.6666.....8888.."
+        ]
+    );
+    let log: Vec<String> = moves.iter().map(Move::to_string).collect();
+    assert_eq!(
+        log,
+        vec![
+            "file 9 moved from 40 to 2",
+            "file 7 moved from 32 to 8",
+            "file 4 moved from 19 to 12",
+            "file 2 moved from 11 to 4"
+        ]
+    );
+    assert_eq!(
+        disk.trace(&BlockLevel)[0].to_string(),
+        "2 blocks of file 9 moved from 40 to 2"
+    );
+
+    //Every move lands on free blocks and the last frame of every strategy is its result, on disks
+    //whose files are already split by `BlockLevel` too
+    let mut disks = vec![
+        disk.clone(),
+        disk.compacted(&BlockLevel),
+        Disk::parse("12345".as_bytes())
+            .unwrap()
+            .compacted(&BlockLevel),
+    ];
+    disks.extend(
+        generated_disk_maps(50, 40)
+            .iter()
+            .map(|map| Disk::parse(map.as_bytes()).unwrap().compacted(&BlockLevel)),
+    );
+    for disk in &disks {
+        for name in ["blocks", "first-fit", "best-fit", "defragment"] {
+            let strategy = get_strategy(name).unwrap();
+            let moves = disk.trace(strategy);
+
+            let mut blocks = disk.blocks();
+            for step in &moves {
+                let (from, to) = (
+                    step.from..step.from + step.length,
+                    step.to..step.to + step.length,
+                );
+                assert!(
+                    blocks[from.clone()]
+                        .iter()
+                        .all(|&block| block == Some(step.id as u64))
+                );
+                blocks[from].fill(None);
+                assert!(
+                    blocks[to.clone()].iter().all(Option::is_none),
+                    "{} on {}: {}",
+                    name,
+                    disk.layout(),
+                    step
+                );
+                blocks[to].fill(Some(step.id as u64));
             }
+
+            let frames = disk.frames(&moves);
+            assert_eq!(frames.last().unwrap(), &disk.compacted(strategy).layout());
         }
-        file_id -= 1;
+    }
+}
+
+#[test]
+fn check_run_length_disk() {
+    let split = Disk::parse("2333 133\n\t121414\n\n  131402\n".as_bytes()).unwrap();
+    assert_eq!(split.layout(), "00...111...2...333.44.5555.6666.777.888899");
+    assert_eq!(part_1(&split), 1928);
+    assert!(Disk::parse("23x3".as_bytes()).is_err());
+    assert!(Disk::parse("2333\n13-3".as_bytes()).is_err());
+
+    //A run far out on a huge disk overflows a u64 checksum
+    let mut huge = Disk {
+        files: vec![vec![], vec![], vec![]],
+        free: Vec::new(),
+        size: (1 << 62) + 4,
+    };
+    huge.files.push(vec![Span {
+        start: 1 << 62,
+        length: 4,
+    }]);
+    assert_eq!(huge.checksum(), 3 * ((1 << 64) + 6));
+
+    //Runs stay runs: a million nines is a few million spans, not billions of blocks
+    let nines = "9".repeat(1_000_000);
+    let disk = Disk::parse(nines.as_bytes()).unwrap();
+    assert_eq!(disk.size, 9_000_000);
+    let compacted = disk.compacted(&BlockLevel);
+    assert_eq!(compacted.free.len(), 1);
+    assert_eq!(compacted.free[0].start, 4_500_000);
+    assert!(
+        compacted
+            .files
+            .iter()
+            .all(|spans| spans.iter().map(|span| span.length).sum::<usize>() == 9)
+    );
+}
+
+#[test]
+fn check_first_fit_against_scanning() {
+    let mut maps = generated_disk_maps(200, 60);
+    maps.extend(generated_disk_maps(3, 20_000));
+
+    for map in maps {
+        let disk = Disk::parse(map.as_bytes()).unwrap();
+        let mut scanned = disk.clone();
+        first_fit_by_scanning(&mut scanned);
+        let compacted = disk.compacted(&FirstFit);
+        assert_eq!(compacted.files, scanned.files, "{}", map);
+        assert_eq!(compacted.checksum(), scanned.checksum());
     }
 
-    calculate_total(&files)
+    //Files longer than any heap's length, which only a hand-built disk has
+    let mut disk = Disk {
+        files: vec![
+            vec![Span {
+                start: 0,
+                length: 1,
+            }],
+            vec![Span {
+                start: 13,
+                length: 1,
+            }],
+            vec![
+                Span {
+                    start: 20,
+                    length: 6,
+                },
+                Span {
+                    start: 30,
+                    length: 6,
+                },
+            ],
+        ],
+        free: Vec::new(),
+        size: 36,
+    };
+    disk.normalise();
+    let mut scanned = disk.clone();
+    first_fit_by_scanning(&mut scanned);
+    assert_eq!(disk.compacted(&FirstFit).files, scanned.files);
+    assert_eq!(
+        scanned.files[2],
+        [Span {
+            start: 1,
+            length: 12
+        }]
+    );
 }
 
+//The scan is quadratic, so this only runs in release builds
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn check_first_fit_against_scanning_a_million_blocks() {
+    let map = generated_disk_maps(20, 25_000).concat();
+    let disk = Disk::parse(map.as_bytes()).unwrap();
+    assert!(disk.size >= 1_000_000, "{}", disk.size);
+
+    let mut scanned = disk.clone();
+    first_fit_by_scanning(&mut scanned);
+    let compacted = disk.compacted(&FirstFit);
+    assert!(compacted.files == scanned.files);
+    assert_eq!(compacted.checksum(), scanned.checksum());
+}
+
+///The largest disk, in blocks, whose trace is drawn rather than logged
+const TRACE_LAYOUT_LIMIT: usize = 200;
+
 fn main() -> Result<()> {
     let file = File::open("inputs/input09.txt")?;
-    let disk = get_disk(file)?;
+    let disk = Disk::parse(io::BufReader::new(file))?;
 
     //Part-1
     println!("{}", part_1(&disk));
@@ -159,5 +920,29 @@ fn main() -> Result<()> {
     println!("{}", part_2(&disk));
     //6478232739671
 
+    //`--strategy=<blocks|first-fit|best-fit|defragment>` compacts with another strategy, and
+    //`--layout` prints the resulting disk
+    if let Some(name) =
+        std::env::args().find_map(|arg| arg.strip_prefix("--strategy=").map(String::from))
+    {
+        let compacted = disk.compacted(get_strategy(&name)?);
+        if std::env::args().any(|arg| arg == "--layout") {
+            println!("{}", compacted.layout());
+        }
+        println!("{}", compacted.checksum());
+
+        //`--trace` shows every step, drawn for small disks and as a move log for large ones
+        if std::env::args().any(|arg| arg == "--trace") {
+            let moves = disk.trace(get_strategy(&name)?);
+            if disk.size <= TRACE_LAYOUT_LIMIT {
+                disk.frames(&moves)
+                    .iter()
+                    .for_each(|frame| println!("{}", frame));
+            } else {
+                moves.iter().for_each(|step| println!("{}", step));
+            }
+        }
+    }
+
     Ok(())
 }
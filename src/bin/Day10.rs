@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{self, BufRead};
 use thiserror::Error;
@@ -8,22 +8,110 @@ use thiserror::Error;
 #[error("Given height cannot be a trailhead!!")]
 struct InvalidTrailHead;
 
+///Height of tiles that aren't digits, which no trail can cross
+const IMPASSABLE: u32 = u32::MAX;
+const SUMMIT: u32 = 9;
+const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, 1), (0, -1)];
+
+///A fixed-size set of summit indices
+#[derive(Clone, Default)]
+struct Bitset(Vec<u64>);
+
+impl Bitset {
+    fn new(size: usize) -> Self {
+        Bitset(vec![0; size.div_ceil(64)])
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    fn union_with(&mut self, other: &Bitset) {
+        self.0
+            .iter_mut()
+            .zip(&other.0)
+            .for_each(|(word, other)| *word |= other);
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(k, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| k * 64 + bit)
+        })
+    }
+}
+
+///What every tile can reach going uphill, worked out once from the summits down
+#[derive(Default)]
+struct Trails {
+    ///How many distinct hiking trails lead from each tile to a summit
+    ratings: Vec<Vec<usize>>,
+    ///Which summits each tile can reach, as indices into `summits`
+    reachable: Vec<Vec<Bitset>>,
+    summits: Vec<(usize, usize)>,
+}
+
+///The hiking trails from one trailhead, found one at a time by a depth-first walk that only
+///steps onto tiles with a trail to a summit, so it never backtracks empty-handed
+struct HikingTrails<'a> {
+    map: &'a Map,
+    path: Vec<(usize, usize)>,
+    ///The tiles still to try after each tile of `path`
+    choices: Vec<Vec<(usize, usize)>>,
+}
+
+impl Iterator for HikingTrails<'_> {
+    type Item = Vec<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.choices.last_mut()?.pop() {
+                None => {
+                    self.choices.pop();
+                    self.path.pop();
+                }
+                Some((x, y)) if self.map.map[x][y] == SUMMIT => {
+                    let mut trail = self.path.clone();
+                    trail.push((x, y));
+                    return Some(trail);
+                }
+                Some((x, y)) => {
+                    self.path.push((x, y));
+                    //Reversed, so that popping tries them in the order of `DIRECTIONS`
+                    let mut next: Vec<(usize, usize)> = self
+                        .map
+                        .uphill(x, y)
+                        .filter(|&(nx, ny)| self.map.trails.ratings[nx][ny] > 0)
+                        .collect();
+                    next.reverse();
+                    self.choices.push(next);
+                }
+            }
+        }
+    }
+}
+
 struct Map {
     rows: usize,
     columns: usize,
     map: Vec<Vec<u32>>,
     trailheads: HashSet<(usize, usize)>,
+    trails: Trails,
 }
 
 impl Map {
-    fn from_file(file: File) -> Result<Self> {
-        let reader = io::BufReader::new(file);
+    fn parse(reader: impl BufRead) -> Result<Self> {
         let map: Vec<Vec<u32>> = reader
             .lines()
             .map(|line| {
                 Ok(line?
                     .chars()
-                    .filter_map(|c| c.to_digit(10))
+                    .map(|c| c.to_digit(10).unwrap_or(IMPASSABLE))
                     .collect::<Vec<u32>>())
             })
             .collect::<Result<Vec<Vec<u32>>>>()?;
@@ -37,56 +125,81 @@ impl Map {
             .flat_map(|(i, row)| {
                 row.iter()
                     .enumerate()
-                    .filter_map(move |(j, &height)| (height == 0).then(|| (i, j)))
+                    .filter_map(move |(j, &height)| (height == 0).then_some((i, j)))
             })
             .collect();
-
-        Ok(Map {
+        let mut map = Map {
             rows,
             columns,
             map,
             trailheads,
-        })
+            trails: Trails::default(),
+        };
+        map.trails = map.trails();
+
+        Ok(map)
     }
 
     fn is_within_map(&self, x: isize, y: isize) -> bool {
         (0..self.rows as isize).contains(&x) && (0..self.columns as isize).contains(&y)
     }
-    ///This is the number of summits that any trailhead can reach
-    fn find_score_of_trailhead(&self, i: usize, j: usize) -> Result<usize, InvalidTrailHead> {
-        if !self.trailheads.contains(&(i, j)) {
-            return Err(InvalidTrailHead);
-        }
 
-        const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, 1), (0, -1)];
-        //A summit is the opposite of a trailhead (where height = 9)
-        let mut summits = HashSet::new();
-        let mut visited = HashSet::new();
-        let mut queue = VecDeque::new();
-        //BFS
-        queue.push_back((i, j));
-        while let Some((x, y)) = queue.pop_front() {
-            visited.insert((i, j));
-
-            if self.map[x][y] == 9 {
-                summits.insert((x, y));
+    ///The tiles one step uphill from `(x, y)`
+    fn uphill(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let height = self.map[x][y];
+        DIRECTIONS.iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            (self.is_within_map(nx, ny) && self.map[nx as usize][ny as usize] == height + 1)
+                .then_some((nx as usize, ny as usize))
+        })
+    }
+
+    ///Goes through the tiles from height 9 down to 0, so every tile's neighbours uphill are done
+    ///before it: a tile's rating is the sum of theirs and its summits are the union of theirs
+    fn trails(&self) -> Trails {
+        let mut by_height = vec![Vec::new(); SUMMIT as usize + 1];
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, &height) in row.iter().enumerate() {
+                if height <= SUMMIT {
+                    by_height[height as usize].push((i, j));
+                }
             }
+        }
 
-            for &(dx, dy) in &DIRECTIONS {
-                let nx = x as isize + dx;
-                let ny = y as isize + dy;
+        let summits = &by_height[SUMMIT as usize];
+        let mut ratings = vec![vec![0; self.columns]; self.rows];
+        let mut reachable = vec![vec![Bitset::default(); self.columns]; self.rows];
+        for (k, &(i, j)) in summits.iter().enumerate() {
+            ratings[i][j] = 1;
+            reachable[i][j] = Bitset::new(summits.len());
+            reachable[i][j].insert(k);
+        }
 
-                if self.is_within_map(nx, ny) && !visited.contains(&(nx as usize, ny as usize)) {
-                    let nx = nx as usize;
-                    let ny = ny as usize;
-                    if self.map[nx][ny] == self.map[x][y] + 1 {
-                        queue.push_back((nx, ny));
-                    }
+        for height in (0..SUMMIT as usize).rev() {
+            for &(x, y) in &by_height[height] {
+                let mut summits_reached = Bitset::new(summits.len());
+                for (nx, ny) in self.uphill(x, y) {
+                    ratings[x][y] += ratings[nx][ny];
+                    summits_reached.union_with(&reachable[nx][ny]);
                 }
+                reachable[x][y] = summits_reached;
             }
         }
 
-        Ok(summits.len())
+        Trails {
+            ratings,
+            reachable,
+            summits: summits.clone(),
+        }
+    }
+
+    ///This is the number of summits that any trailhead can reach
+    fn find_score_of_trailhead(&self, i: usize, j: usize) -> Result<usize, InvalidTrailHead> {
+        if !self.trailheads.contains(&(i, j)) {
+            return Err(InvalidTrailHead);
+        }
+
+        Ok(self.trails.reachable[i][j].len())
     }
 
     fn part_1(&self) -> usize {
@@ -96,65 +209,160 @@ impl Map {
             .sum()
     }
 
-    fn dfs(
-        &self,
-        x: usize,
-        y: usize,
-        visited: &mut HashSet<(usize, usize)>,
-        directions: &[(isize, isize)],
-    ) -> usize {
-        if visited.contains(&(x, y)) {
-            return 0;
-        }
-
-        visited.insert((x, y));
-
-        if self.map[x][y] == 9 {
-            visited.remove(&(x, y));
-            return 1;
+    ///This is the number of distinct hiking trails that start at a trailhead
+    fn find_rating_of_trailhead(&self, i: usize, j: usize) -> Result<usize, InvalidTrailHead> {
+        if !self.trailheads.contains(&(i, j)) {
+            return Err(InvalidTrailHead);
         }
 
-        let mut paths = 0;
-        for &(dx, dy) in directions {
-            let nx = x as isize + dx;
-            let ny = y as isize + dy;
+        Ok(self.trails.ratings[i][j])
+    }
 
-            if self.is_within_map(nx, ny) {
-                let nx = nx as usize;
-                let ny = ny as usize;
+    fn part_2(&self) -> usize {
+        self.trailheads
+            .iter()
+            .flat_map(|&(i, j)| self.find_rating_of_trailhead(i, j))
+            .sum()
+    }
 
-                if self.map[nx][ny] == self.map[x][y] + 1 {
-                    paths += self.dfs(nx, ny, visited, directions);
-                }
-            }
+    ///Every distinct hiking trail from the trailhead, lazily, as ratings can be large
+    fn hiking_trails(&self, i: usize, j: usize) -> Result<HikingTrails<'_>, InvalidTrailHead> {
+        if !self.trailheads.contains(&(i, j)) {
+            return Err(InvalidTrailHead);
         }
 
-        visited.remove(&(x, y));
-        paths
+        Ok(HikingTrails {
+            map: self,
+            path: Vec::new(),
+            choices: vec![vec![(i, j)]],
+        })
     }
 
-    fn find_rating_of_trailhead(&self, i: usize, j: usize) -> Result<usize, InvalidTrailHead> {
+    ///The summits the trailhead reaches, in reading order
+    fn summits(&self, i: usize, j: usize) -> Result<Vec<(usize, usize)>, InvalidTrailHead> {
         if !self.trailheads.contains(&(i, j)) {
             return Err(InvalidTrailHead);
         }
 
-        const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, 1), (0, -1)];
-        let mut visited = HashSet::new();
+        Ok(self.trails.reachable[i][j]
+            .iter()
+            .map(|k| self.trails.summits[k])
+            .collect())
+    }
 
-        Ok(self.dfs(i, j, &mut visited, &DIRECTIONS))
+    ///The map with only the heights along `trail` shown, as in the puzzle
+    fn render_trail(&self, trail: &[(usize, usize)]) -> String {
+        let mut grid = vec![vec!['.'; self.columns]; self.rows];
+        for &(x, y) in trail {
+            grid[x][y] = char::from_digit(self.map[x][y], 10).unwrap_or('.');
+        }
+
+        grid.into_iter()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect()
     }
 
-    fn part_2(&self) -> usize {
-        self.trailheads
-            .iter()
-            .flat_map(|&(i, j)| self.find_rating_of_trailhead(i, j))
-            .sum()
+    ///Every trailhead in reading order with its score and rating
+    fn trailhead_report(&self) -> Vec<((usize, usize), usize, usize)> {
+        let mut trailheads: Vec<(usize, usize)> = self.trailheads.iter().copied().collect();
+        trailheads.sort_unstable();
+
+        trailheads
+            .into_iter()
+            .map(|(i, j)| {
+                (
+                    (i, j),
+                    self.trails.reachable[i][j].len(),
+                    self.trails.ratings[i][j],
+                )
+            })
+            .collect()
     }
 }
 
+#[cfg(test)]
+const EXAMPLE: &str = "89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732";
+
+#[test]
+fn check_scores_and_ratings() {
+    let map = Map::parse(EXAMPLE.as_bytes()).unwrap();
+    assert_eq!(map.part_1(), 36);
+    assert_eq!(map.part_2(), 81);
+
+    let report = map.trailhead_report();
+    let scores: Vec<usize> = report.iter().map(|&(_, score, _)| score).collect();
+    let ratings: Vec<usize> = report.iter().map(|&(_, _, rating)| rating).collect();
+    assert_eq!(scores, vec![5, 6, 5, 3, 1, 3, 5, 3, 5]);
+    assert_eq!(ratings, vec![20, 24, 10, 4, 1, 4, 5, 8, 5]);
+    assert!(map.find_score_of_trailhead(0, 0).is_err());
+
+    //Impassable tiles keep their column, and a summit reached two ways still scores once
+    let map =
+        Map::parse("..90..9\n...1.98\n...2..7\n6543456\n765.987\n876....\n987....".as_bytes())
+            .unwrap();
+    assert_eq!(map.find_score_of_trailhead(0, 3).unwrap(), 4);
+    assert_eq!(map.find_rating_of_trailhead(0, 3).unwrap(), 13);
+    let map = Map::parse("012345\n123456\n234567\n345678\n456789".as_bytes()).unwrap();
+    assert_eq!(map.find_score_of_trailhead(0, 0).unwrap(), 1);
+    assert_eq!(map.find_rating_of_trailhead(0, 0).unwrap(), 126);
+}
+
+#[test]
+fn check_hiking_trails() {
+    let map =
+        Map::parse(".....0.\n..4321.\n..5..2.\n..6543.\n..7..4.\n..8765.\n..9....".as_bytes())
+            .unwrap();
+    let trails: Vec<Vec<(usize, usize)>> = map.hiking_trails(0, 5).unwrap().collect();
+    assert_eq!(trails.len(), 3);
+    assert_eq!(map.summits(0, 5).unwrap(), vec![(6, 2)]);
+    assert_eq!(
+        map.render_trail(&trails[0]),
+        ".....0.
+.....1.
+.....2.
+.....3.
+.....4.
+..8765.
+..9....
+"
+    );
+
+    //Every trail is a distinct walk up from 0 to 9, and there are as many as the rating
+    let map = Map::parse(EXAMPLE.as_bytes()).unwrap();
+    for ((i, j), score, rating) in map.trailhead_report() {
+        let trails: HashSet<Vec<(usize, usize)>> = map.hiking_trails(i, j).unwrap().collect();
+        assert_eq!(trails.len(), rating);
+        for trail in &trails {
+            assert_eq!(trail.len(), 10);
+            for (height, window) in trail.windows(2).enumerate() {
+                let ((x, y), (nx, ny)) = (window[0], window[1]);
+                assert_eq!(map.map[x][y], height as u32);
+                assert_eq!(x.abs_diff(nx) + y.abs_diff(ny), 1);
+            }
+        }
+
+        let summits: HashSet<(usize, usize)> = trails.iter().map(|trail| trail[9]).collect();
+        assert_eq!(summits.len(), score);
+        assert_eq!(summits, map.summits(i, j).unwrap().into_iter().collect());
+    }
+    assert!(map.hiking_trails(0, 0).is_err());
+    assert!(map.summits(0, 0).is_err());
+
+    //Only as much of a huge rating is walked as is asked for
+    let map = Map::parse("0123456789\n1234567890".as_bytes()).unwrap();
+    assert_eq!(map.hiking_trails(0, 0).unwrap().take(1).count(), 1);
+}
+
 fn main() -> Result<()> {
     let file = File::open("inputs/input10.txt")?;
-    let map = Map::from_file(file)?;
+    let map = Map::parse(io::BufReader::new(file))?;
 
     //Part-1
     println!("{}", map.part_1());
@@ -164,5 +372,33 @@ fn main() -> Result<()> {
     println!("{}", map.part_2());
     //1459
 
+    //`--report` lists every trailhead with its score and rating
+    if std::env::args().any(|arg| arg == "--report") {
+        for (trailhead, score, rating) in map.trailhead_report() {
+            println!("{:?}: score {}, rating {}", trailhead, score, rating);
+        }
+    }
+
+    //`--trails=r,c` lists the summits and trails of a trailhead, and `--render=r,c,n` draws its
+    //`n`th trail
+    let coordinates = |prefix: &str| -> Option<Vec<usize>> {
+        std::env::args().find_map(|arg| {
+            arg.strip_prefix(prefix)
+                .map(|list| list.split(',').filter_map(|n| n.parse().ok()).collect())
+        })
+    };
+    if let Some(&[i, j]) = coordinates("--trails=").as_deref() {
+        println!("Summits: {:?}", map.summits(i, j)?);
+        for trail in map.hiking_trails(i, j)? {
+            println!("{:?}", trail);
+        }
+    }
+    if let Some(&[i, j, n]) = coordinates("--render=").as_deref() {
+        match map.hiking_trails(i, j)?.nth(n) {
+            Some(trail) => print!("{}", map.render_trail(&trail)),
+            None => println!("Trailhead {:?} has no trail {}", (i, j), n),
+        }
+    }
+
     Ok(())
-}
\ No newline at end of file
+}